@@ -1,9 +1,11 @@
 use std::error::Error;
 
-use super::json_objects::Puzzle;
+use super::json_objects::{Puzzle, PuzzleSource};
 use super::json_objects::parse_direct_puzzle;
 
 use crate::notation_utils;
+use crate::notation_utils::notation_error::NotationError;
+use crate::utils::termcolors::{Color, color};
 
 async fn get_puzzle_from_id(client: &reqwest::Client, id: String) -> Result<Puzzle, Box<dyn Error>> {
     let response = client
@@ -26,21 +28,38 @@ async fn get_puzzle_from_id(client: &reqwest::Client, id: String) -> Result<Puzz
         rating: parsed_puzzle.puzzle.rating,
         solution: parsed_puzzle.puzzle.solution,
         themes: parsed_puzzle.puzzle.themes,
-        fen: notation_utils::pgn_to_fen::pgn_to_fen(&parsed_puzzle.game.pgn),
-        imported_directly: Some(true),
+        fen: notation_utils::pgn_to_fen::pgn_to_fen(&parsed_puzzle.game.pgn)?,
+        source: PuzzleSource::Id,
     })
 }
 
 
-pub async fn get_from_ids(ids: Vec<String>, ignore: Vec<String>) -> Result<Vec<Puzzle>, Box<dyn Error>> {
+pub async fn get_from_ids(ids: Vec<String>, staged: &[Puzzle]) -> Result<Vec<Puzzle>, Box<dyn Error>> {
     let client = reqwest::Client::new();
 
     let mut puzzles: Vec<Puzzle> = Vec::new();
     let mut total_duplicates: usize = 0;
 
     for id in ids {
-        if !ignore.contains(&id) && !puzzles.iter().any(|puzzle| puzzle.id == id) {
-            puzzles.push(get_puzzle_from_id(&client, id).await?);
+        if !staged.iter().chain(&puzzles).any(|puzzle| puzzle.id == id) {
+            match get_puzzle_from_id(&client, id.clone()).await {
+                // Only known once it's fetched, since a different ID can still be the same position
+                Ok(puzzle) => match staged.iter().chain(&puzzles).find(|other| puzzle.is_duplicate_of(other)) {
+                    Some(other) => {
+                        let warning_msg = format!(
+                            "Skipping https://lichess.org/training/{}: same position and solution as {}",
+                            id, other.id
+                        );
+                        eprintln!("{}", color(&warning_msg, Color::Yellow));
+                    }
+                    None => puzzles.push(puzzle)
+                },
+                Err(e) if e.is::<NotationError>() => {
+                    let warning_msg = format!("Skipping https://lichess.org/training/{}: {}", id, e);
+                    eprintln!("{}", color(&warning_msg, Color::Yellow));
+                }
+                Err(e) => return Err(e)
+            }
         } else {
             total_duplicates += 1;
         }
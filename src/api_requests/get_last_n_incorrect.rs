@@ -1,6 +1,6 @@
 use std::error::Error;
 
-use super::json_objects::Puzzle;
+use super::json_objects::{Puzzle, PuzzleSource};
 use super::json_objects::parse_puzzle;
 
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
@@ -11,9 +11,8 @@ async fn get_puzzle_history_incorrect_page(
     client: &reqwest::Client,
     pat: String,
     max: i32,
-    before_date: i64,
-    ignore: &Vec<String>
-) -> Result<(Vec<Puzzle>, i64, usize), Box<dyn Error>> {
+    before_date: i64
+) -> Result<(Vec<Puzzle>, i64), Box<dyn Error>> {
     let mut headers = HeaderMap::new();
     headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", pat))?);
 
@@ -40,29 +39,24 @@ async fn get_puzzle_history_incorrect_page(
     let puzzle_attempt_strings = body.lines();
     let mut incorrect_puzzles = Vec::new();
     let mut last_date = 0;
-    let mut duplicates = 0;
 
     for puzzle_attempt_string in puzzle_attempt_strings {
         match parse_puzzle(puzzle_attempt_string) {
             Ok(mut puzzle_attempt) if !puzzle_attempt.win => {
-                if ignore.contains(&puzzle_attempt.puzzle.id) {
-                    duplicates += 1;
-                } else {
-                    puzzle_attempt.puzzle.imported_directly = None;
-                    last_date = puzzle_attempt.date;
-                    incorrect_puzzles.push(puzzle_attempt.puzzle);
-                }
+                puzzle_attempt.puzzle.source = PuzzleSource::History;
+                last_date = puzzle_attempt.date;
+                incorrect_puzzles.push(puzzle_attempt.puzzle);
             }
             Ok(_) => {}
             Err(e) => return Err(e.into()),
         }
     }
 
-    Ok((incorrect_puzzles, last_date, duplicates))
+    Ok((incorrect_puzzles, last_date))
 }
 
 
-pub async fn get_last_n_incorrect(pat: String, n: usize, ignore: Vec<String>) -> Result<Vec<Puzzle>, Box<dyn Error>> {
+pub async fn get_last_n_incorrect(pat: String, n: usize, staged: &[Puzzle]) -> Result<Vec<Puzzle>, Box<dyn Error>> {
     let client = reqwest::Client::new();
 
     let mut incorrect_puzzles: Vec<Puzzle> = Vec::new();
@@ -71,22 +65,25 @@ pub async fn get_last_n_incorrect(pat: String, n: usize, ignore: Vec<String>) ->
     let mut total_duplicates: usize = 0;
 
     while size < n {
-        let page_data = get_puzzle_history_incorrect_page(&client, pat.clone(), PAGE_SIZE, before_date, &ignore).await?;
+        let page_data = get_puzzle_history_incorrect_page(&client, pat.clone(), PAGE_SIZE, before_date).await?;
         let page = page_data.0;
         before_date = page_data.1;
 
-        total_duplicates += page_data.2;
-
         if page.is_empty() { break; }
 
         for puzzle in page {
-            incorrect_puzzles.push(puzzle);
-            size += 1;
+            if staged.iter().chain(&incorrect_puzzles).any(|other| puzzle.is_duplicate_of(other)) {
+                total_duplicates += 1;
+            } else {
+                incorrect_puzzles.push(puzzle);
+                size += 1;
+            }
         }
     }
 
     let plural_char = if total_duplicates == 1 { "" } else { "s" };
-    if total_duplicates > 0 { println!("\nSkipping {} duplicate ID{}", total_duplicates, plural_char); }
+    if total_duplicates > 0 { println!("\nSkipping {} duplicate puzzle{}", total_duplicates, plural_char); }
 
-    Ok(incorrect_puzzles[0..n].to_vec())
+    incorrect_puzzles.truncate(n);
+    Ok(incorrect_puzzles)
 }
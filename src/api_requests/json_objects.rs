@@ -1,5 +1,23 @@
 use serde::Deserialize;
 use crate::notation_utils;
+use crate::notation_utils::fen_error::read_valid_fen;
+use crate::notation_utils::notation_error::NotationError;
+use crate::notation_utils::pgn_game::{PgnGame, PgnNode};
+use crate::notation_utils::pgn_writer::write_pgn;
+use crate::notation_utils::solution_check::{check_solution, SolutionError};
+use crate::notation_utils::move_notation::{convert_line, MoveStyle, Notation, PieceLetters};
+use crate::notation_utils::position::{PieceColor, Position};
+
+// Where a staged puzzle came from, so its study comment can link back to it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PuzzleSource {
+    #[default]
+    History,
+    Id,
+    Database,
+    ChessCom { link: Option<String> },
+    Epd
+}
 
 #[derive(Deserialize)]
 #[derive(Clone)]
@@ -9,7 +27,8 @@ pub struct Puzzle {
     pub solution: Vec<String>,
     pub themes: Vec<String>,
     pub fen: String,
-    pub imported_directly: Option<bool>
+    #[serde(skip)]
+    pub source: PuzzleSource
 }
 
 #[derive(Deserialize)]
@@ -38,7 +57,25 @@ pub struct DirectPuzzleGameData {
 }
 
 impl Puzzle {
-    fn formatted_themes(&self) -> String {
+    // https://lichess.org/training/xw2Nb, for tests to change whichever fields they check
+    #[cfg(test)]
+    pub fn sample() -> Self {
+        Puzzle {
+            id: "xw2Nb".to_string(),
+            rating: 1500,
+            solution: vec!["e5f6".to_string(), "d8f6".to_string(), "c3e4".to_string()],
+            themes: vec!["fork".to_string(), "middlegame".to_string()],
+            fen: "r2qk2r/p1pp2pp/1p2p3/2b1Pp2/5PPn/2NQ2K1/PPP4P/R1B2R2 w kq f6 0 15".to_string(),
+            source: PuzzleSource::Id
+        }
+    }
+
+    // Imported puzzles don't always come with a rating
+    pub fn formatted_rating(&self) -> String {
+        if self.rating > 0 { self.rating.to_string() } else { "?".to_string() }
+    }
+
+    pub fn formatted_themes(&self) -> String {
         self.themes.iter().map(|s| {
             let mut result = String::new();
             let mut was_lower = false;
@@ -58,100 +95,118 @@ impl Puzzle {
         }).collect::<Vec<_>>().join(", ")
     }
 
-    pub fn info_comment(&self) -> String {
-        let link: String = format!("https://lichess.org/training/{}", self.id);
-        let source: &str;
-        if self.imported_directly == Some(true) {
-            source = "(from ID)";
-        } else {
-            source = "(from puzzle history)"
+    // Falls back to the bare ID for sources that don't come with a link
+    pub fn link(&self) -> String {
+        match &self.source {
+            PuzzleSource::History | PuzzleSource::Id | PuzzleSource::Database => format!("https://lichess.org/training/{}", self.id),
+            PuzzleSource::ChessCom { link: Some(link) } => link.clone(),
+            PuzzleSource::ChessCom { link: None } | PuzzleSource::Epd => self.id.clone()
+        }
+    }
+
+    pub fn info_comment(&self, position_info: bool) -> String {
+        let source = match self.source {
+            PuzzleSource::History => "(from puzzle history)",
+            PuzzleSource::Id => "(from ID)",
+            PuzzleSource::Database => "(from puzzle database)",
+            PuzzleSource::ChessCom { .. } => "(from chess.com)",
+            PuzzleSource::Epd => "(from EPD)"
         };
-        let comment: String = format!(
+        let mut comment: String = format!(
             "{} {}\nRating - {}\nThemes - {}",
-            link, source.to_string(), self.rating, self.formatted_themes()
+            self.link(), source, self.formatted_rating(), self.formatted_themes()
         );
+        if let (true, Ok(position)) = (position_info, Position::from_fen(&self.fen)) {
+            comment.push_str(&format!(
+                "\nMaterial - {}\nPhase - {} ({} pieces)",
+                position.material_signature(), position.game_phase().name(), position.piece_count()
+            ));
+        }
 
-        return comment;
+        comment
     }
 
-    pub fn build_pgn(&self, puzzle_num: usize) -> String {
-        let headers: String = format!(
-            "[Event \"Puzzle {}\"]\n\
-             [Result \"*\"]\n\
-             [Variant \"From Position\"]\n\
-             [ECO \"?\"]\n\
-             [Opening \"?\"]\n\
-             [FEN \"{}\"]\n\
-             [SetUp \"1\"]\n\
-             [ChapterMode \"gamebook\"]",
-            puzzle_num, self.fen
-        );
+    // The solution as numbered movetext, like "15. exf6 Qxf6 16. Ne4"
+    pub fn solution_text(&self, notation: Notation) -> Result<String, NotationError> {
+        let moves = convert_line(&self.fen, &self.solution, MoveStyle::Uci.into(), notation)?;
+        let first_ply = read_valid_fen(&self.fen)?.ply();
+
+        let mut words = Vec::new();
+        for (i, text) in moves.iter().enumerate() {
+            let ply = first_ply + i;
+            if ply.is_multiple_of(2) {
+                words.push(format!("{}.", ply / 2 + 1));
+            } else if i == 0 {
+                words.push(format!("{}...", ply / 2 + 1));
+            }
+            words.push(text.clone());
+        }
+        Ok(words.join(" "))
+    }
+
+    // None when the FEN doesn't parse, so broken puzzles are never duplicates of each other
+    pub fn position_hash(&self) -> Option<u64> {
+        Position::from_fen(&self.fen).ok().map(|position| position.zobrist_hash())
+    }
+
+    // The same starting position and solution is the same puzzle to study, whatever the IDs say
+    pub fn is_duplicate_of(&self, other: &Puzzle) -> bool {
+        self.link() == other.link()
+            || (self.solution == other.solution
+                && self.position_hash().is_some()
+                && self.position_hash() == other.position_hash())
+    }
+
+    // Catches puzzles that would only fail once they're being uploaded
+    pub fn validate(&self) -> Result<(), SolutionError> {
+        check_solution(&self.fen, &self.solution, &self.themes)
+    }
+
+    pub fn build_pgn(&self, puzzle_num: usize, notation: Notation, position_info: bool) -> Result<String, NotationError> {
+        // The movetext has to stay English SAN for lichess to read it; only the comments are localized
+        let pgn_moves = notation_utils::fen_to_pgn::fen_to_pgn(self.fen.clone(), self.solution.clone(), PieceLetters::English)?;
+        let position = read_valid_fen(&self.fen)?;
+        let variant = if position.chess960 { "Chess960" } else { "From Position" };
 
-        let fen_regions: Vec<&str> = self.fen.split_whitespace().collect(); 
-        let puzzle_color = fen_regions[1];
-
-        let pgn_moves = notation_utils::fen_to_pgn::fen_to_pgn(self.fen.clone(), self.solution.clone());
-        let mut pgn_output: String;
-
-        let fen_regions: Vec<&str> = self.fen
-            .split(|c: char| c == '/' || c.is_whitespace())
-            .collect();
-
-        let ep_flag = fen_regions[10];
-        
-        let last_move = if ep_flag != "-" {
-            let (file, rank) = ep_flag.split_at(1);
-            let target_rank = rank.parse::<u8>().unwrap();
-            let move_rank = if target_rank == 3 { 4 } else { 5 };
-            format!("(Last move: {}{})\n", file, move_rank)
-        } else {
-            "".to_string()
+        let mut game = PgnGame {
+            headers: vec![
+                ("Event".to_string(), format!("Puzzle {}", puzzle_num)),
+                ("Variant".to_string(), variant.to_string()),
+                ("ECO".to_string(), "?".to_string()),
+                ("Opening".to_string(), "?".to_string()),
+                ("FEN".to_string(), self.fen.clone()),
+                ("SetUp".to_string(), "1".to_string()),
+                ("ChapterMode".to_string(), "gamebook".to_string())
+            ],
+            ..Default::default()
         };
 
-        // lazy alert!
-        if puzzle_color == "w" {
-            pgn_output = format!("{{ White to move \n{}}}\n", last_move).to_string();
-            for (i, mv) in pgn_moves.iter().enumerate() {
-                let move_number = i / 2 + 1;
-                let is_player_move = i % 2 == 0;
-    
-                if i == self.solution.len() - 1 {
-                    pgn_output.push_str(&format!("{}.", move_number));
-                    pgn_output.push_str(&format!(" {}", mv));
-                    pgn_output.push_str(&format!(" {{ {} }} ", self.info_comment()));
-                } else if is_player_move {
-                    pgn_output.push_str(&format!("{}.", move_number));
-                    pgn_output.push_str(&format!(" {}", mv));
-                    pgn_output.push_str(" { Correct } ");
-                } else {
-                    pgn_output.push_str(&format!("{}...", move_number));
-                    pgn_output.push_str(&format!(" {}", mv));
-                    pgn_output.push_str(" { White to move } ");
-                }
-            }
-            return format!("{}\n\n{}", headers, pgn_output);
-        } else {
-            pgn_output = format!("{{ Black to move \n{}}}\n", last_move).to_string();
-            for (i, mv) in pgn_moves.iter().enumerate() {
-                let move_number = (i + 1) / 2 + 1;
-                let is_player_move = i % 2 == 1;
-    
-                if i == self.solution.len() - 1 {
-                    pgn_output.push_str(&format!("{}...", move_number));
-                    pgn_output.push_str(&format!(" {}", mv));
-                    pgn_output.push_str(&format!(" {{ {} }} *", self.info_comment()));
-                } else if is_player_move {
-                    pgn_output.push_str(&format!("{}.", move_number));
-                    pgn_output.push_str(&format!(" {}", mv));
-                    pgn_output.push_str(" { Black to move } ");
-                } else {
-                    pgn_output.push_str(&format!("{}...", move_number));
-                    pgn_output.push_str(&format!(" {}", mv));
-                    pgn_output.push_str(" { Correct } ");
-                }
-            }
-            return format!("{}\n\n{}", headers, pgn_output);
+        let side_to_move = if position.turn == PieceColor::White { "White to move" } else { "Black to move" };
+        let mut intro = side_to_move.to_string();
+        if let Some((before, last_move)) = position.before_double_push() {
+            intro.push_str(&format!("\n(Last move: {})", notation.format(&before, &last_move)));
+        }
+        game.mainline.comment = Some(intro);
+
+        // lichess shows the movetext in English SAN, so any other notation gets the solution spelled out
+        let mut info = self.info_comment(position_info);
+        if notation != Notation::default() {
+            info.push_str(&format!("\nSolution - {}", self.solution_text(notation)?));
         }
+
+        for (i, san) in pgn_moves.iter().enumerate() {
+            let mut node = PgnNode::new(san);
+            node.comment = Some(if i == pgn_moves.len() - 1 {
+                info.clone()
+            } else if i.is_multiple_of(2) {
+                "Correct".to_string()
+            } else {
+                side_to_move.to_string()
+            });
+            game.mainline.moves.push(node);
+        }
+
+        Ok(write_pgn(&game))
     }
 }
 
@@ -164,3 +219,81 @@ pub fn parse_direct_puzzle(json_str: &str) -> serde_json::Result<DirectPuzzleDat
     let direct_puzzle_data: DirectPuzzleData = serde_json::from_str(json_str)?;
     Ok(direct_puzzle_data)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_pgn() {
+        let puzzle = Puzzle::sample();
+
+        assert_eq!(
+            puzzle.build_pgn(3, Notation::default(), false).unwrap(),
+            "[Event \"Puzzle 3\"]\n\
+             [Result \"*\"]\n\
+             [Variant \"From Position\"]\n\
+             [ECO \"?\"]\n\
+             [Opening \"?\"]\n\
+             [FEN \"r2qk2r/p1pp2pp/1p2p3/2b1Pp2/5PPn/2NQ2K1/PPP4P/R1B2R2 w kq f6 0 15\"]\n\
+             [SetUp \"1\"]\n\
+             [ChapterMode \"gamebook\"]\n\
+             \n\
+             { White to move\n\
+             (Last move: f5) } 15. exf6 { Correct } 15... Qxf6 { White to move } 16. Ne4 {\n\
+             https://lichess.org/training/xw2Nb (from ID)\n\
+             Rating - 1500\n\
+             Themes - Fork, Middlegame } *"
+        );
+
+        let with_position_info = puzzle.build_pgn(3, Notation::default(), true).unwrap();
+        assert!(with_position_info.contains("Themes - Fork, Middlegame\nMaterial - KQRRBNPPPPPPPvKQRRBNPPPPPPPP\nPhase - Middlegame (27 pieces) } *"));
+
+        let german_lan = Notation { style: MoveStyle::Lan, letters: PieceLetters::German };
+        let pgn = puzzle.build_pgn(3, german_lan, false).unwrap();
+        assert!(pgn.contains("(Last move: f7-f5) } 15. exf6 { Correct } 15... Qxf6"));
+        assert!(pgn.contains("Themes - Fork, Middlegame\nSolution - 15. e5xf6 Dd8xf6 16. Sc3-e4 } *"));
+    }
+
+    #[test]
+    fn test_build_pgn_chess960() {
+        let puzzle = Puzzle {
+            solution: vec!["e1g1".to_string()],
+            fen: "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1".to_string(),
+            ..Puzzle::sample()
+        };
+
+        let pgn = puzzle.build_pgn(1, Notation::default(), false).unwrap();
+        assert!(pgn.contains("[Variant \"Chess960\"]"));
+        assert!(pgn.contains("1. O-O {"));
+    }
+
+    #[test]
+    fn test_solution_text() {
+        let mut puzzle = Puzzle::sample();
+        assert_eq!(puzzle.solution_text(Notation::default()).unwrap(), "15. exf6 Qxf6 16. Ne4");
+        let german = Notation { style: MoveStyle::San, letters: PieceLetters::German };
+        assert_eq!(puzzle.solution_text(german).unwrap(), "15. exf6 Dxf6 16. Se4");
+
+        puzzle.fen = "6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 30".to_string();
+        puzzle.solution = vec!["h7h6".to_string(), "a1a8".to_string()];
+        assert_eq!(puzzle.solution_text(Notation::default()).unwrap(), "30... h6 31. Ra8+");
+    }
+
+    #[test]
+    fn test_is_duplicate_of() {
+        let puzzle = |id: &str, fen: &str| Puzzle {
+            id: id.to_string(),
+            solution: vec!["a1a8".to_string()],
+            fen: fen.to_string(),
+            source: PuzzleSource::History,
+            ..Puzzle::sample()
+        };
+
+        let original = puzzle("aaaaa", "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        assert!(puzzle("bbbbb", "6k1/5ppp/8/8/8/8/8/R5K1 w - - 3 27").is_duplicate_of(&original));
+        assert!(puzzle("aaaaa", "6k1/5ppp/8/8/8/8/8/R6K w - - 0 1").is_duplicate_of(&original));
+        assert!(!puzzle("bbbbb", "6k1/5ppp/8/8/8/8/8/R6K w - - 0 1").is_duplicate_of(&original));
+        assert!(!puzzle("bbbbb", "not a fen").is_duplicate_of(&puzzle("ccccc", "not a fen")));
+    }
+}
@@ -6,7 +6,10 @@ use super::json_objects::Puzzle;
 use serde::Serialize;
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
 
+use crate::notation_utils::move_notation::Notation;
+use crate::notation_utils::notation_error::NotationError;
 use crate::utils::progress_bar::{inner_progress_bar, PROGRESS_BAR_WIDTH};
+use crate::utils::termcolors::{Color, color};
 
 #[derive(Serialize)]
 struct ImportPgnRequest {
@@ -17,24 +20,39 @@ struct ImportPgnRequest {
     mode: String
 }
 
-fn concatenate_pgn(puzzles: Vec<Puzzle>, offset_index: bool) -> String {
+fn concatenate_pgn(puzzles: Vec<Puzzle>, offset_index: bool, notation: Notation, position_info: bool) -> Result<String, NotationError> {
     let index_offset = if offset_index {2} else {1};
     let pgn_strings: Vec<String> = puzzles
         .iter()
         .enumerate()
-        .map(|(index, puzzle)| puzzle.build_pgn(index + index_offset)) 
-        .collect();
+        .map(|(index, puzzle)| puzzle.build_pgn(index + index_offset, notation, position_info)) 
+        .collect::<Result<_, _>>()?;
     
-    pgn_strings.join("\n\n")
+    Ok(pgn_strings.join("\n\n"))
 }
 
-async fn post_puzzles_to_study(client: &reqwest::Client, pat: String, study_id: &str, puzzles: Vec<Puzzle>, offset_index: bool) -> Result<(), Box<dyn Error>> {
+// The puzzles that become chapters, in chapter order, warning about the rest
+pub fn skip_unbuildable(puzzles: Vec<Puzzle>, notation: Notation, position_info: bool) -> Vec<Puzzle> {
+    puzzles
+        .into_iter()
+        .filter(|puzzle| match puzzle.build_pgn(1, notation, position_info) {
+            Ok(_) => true,
+            Err(e) => {
+                let warning_msg = format!("Skipping {}: {}", puzzle.link(), e);
+                eprintln!("{}", color(&warning_msg, Color::Yellow));
+                false
+            }
+        })
+        .collect()
+}
+
+async fn post_puzzles_to_study(client: &reqwest::Client, pat: String, study_id: &str, puzzles: Vec<Puzzle>, offset_index: bool, notation: Notation, position_info: bool) -> Result<(), Box<dyn Error>> {
     let len = puzzles.clone().len();
     
     let mut headers = HeaderMap::new();
     headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", pat))?);
 
-    let pgn_strings = concatenate_pgn(puzzles, offset_index);
+    let pgn_strings = concatenate_pgn(puzzles, offset_index, notation, position_info)?;
 
     let form_puzzle_name = if !offset_index {"Puzzle 1".to_string()} else {"Puzzle 2".to_string()};
 
@@ -85,7 +103,7 @@ async fn get_study_chapter_ids(client: &reqwest::Client, pat: String, study_id:
         
     for line in body.lines() {
         if line.starts_with("[Site ") {          
-            if let Some(id) = line.split('/').last() {
+            if let Some(id) = line.split('/').next_back() {
                 if let Some(trimmed) = id.strip_suffix("\"]") {
                     ids.push(trimmed.to_string());
                 }
@@ -135,20 +153,25 @@ async fn clear_study(client: &reqwest::Client, pat: String, study_id: &str, mut
     Ok(())
 }
 
-pub async fn post_overwrite(pat: String, study_id: &str, mut puzzles: Vec<Puzzle>) -> Result<(), Box<dyn Error>> {
+pub async fn post_overwrite(pat: String, study_id: &str, puzzles: Vec<Puzzle>, notation: Notation, position_info: bool) -> Result<(), Box<dyn Error>> {
     let client = reqwest::Client::new();
 
+    let mut puzzles = skip_unbuildable(puzzles, notation, position_info);
+    if puzzles.is_empty() {
+        return Err(Box::from("None of the staged puzzles could be converted to PGN"));
+    }
+
     println!("Getting study chapter IDs");
     let chapter_ids = get_study_chapter_ids(&client, pat.clone(), study_id).await?;
-    let minimum_chapter_id = (&chapter_ids[0]).to_string();
+    let minimum_chapter_id = chapter_ids[0].to_string();
     let first_puzzle = puzzles.remove(0);
 
     clear_study(&client, pat.clone(), study_id, chapter_ids).await?;
     
-    post_puzzles_to_study(&client, pat.clone(), study_id, vec![first_puzzle], false).await?;
+    post_puzzles_to_study(&client, pat.clone(), study_id, vec![first_puzzle], false, notation, position_info).await?;
     clear_chapter(&client, pat.clone(), study_id, minimum_chapter_id).await?;
     println!("Uploading staged puzzles");
-    post_puzzles_to_study(&client, pat.clone(), study_id, puzzles, true).await?;
+    post_puzzles_to_study(&client, pat.clone(), study_id, puzzles, true, notation, position_info).await?;
 
     Ok(())
 }
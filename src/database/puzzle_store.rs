@@ -0,0 +1,298 @@
+use std::error::Error;
+
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+
+use crate::api_requests::json_objects::{Puzzle, PuzzleSource};
+use crate::imports::puzzle_db::{DbFilter, DbPuzzle};
+use crate::notation_utils::material::material_matches;
+use crate::notation_utils::position::Position;
+
+// Themes and openings get their own tables so a filter on them is an index lookup rather than a scan
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS puzzles (
+    id TEXT PRIMARY KEY,
+    fen TEXT NOT NULL,
+    solution TEXT NOT NULL,
+    rating INTEGER NOT NULL,
+    popularity INTEGER NOT NULL,
+    plays INTEGER NOT NULL,
+    themes TEXT NOT NULL,
+    material TEXT NOT NULL DEFAULT '',
+    phase TEXT NOT NULL DEFAULT '',
+    pieces INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS puzzle_themes (
+    puzzle_id TEXT NOT NULL REFERENCES puzzles(id),
+    theme TEXT NOT NULL,
+    PRIMARY KEY (theme, puzzle_id)
+);
+CREATE TABLE IF NOT EXISTS puzzle_openings (
+    puzzle_id TEXT NOT NULL REFERENCES puzzles(id),
+    opening TEXT NOT NULL,
+    PRIMARY KEY (opening, puzzle_id)
+);
+CREATE TABLE IF NOT EXISTS seen (
+    id TEXT PRIMARY KEY
+);
+CREATE INDEX IF NOT EXISTS puzzles_rating ON puzzles(rating);
+";
+
+// Databases made before puzzles were classified by material and phase
+const CLASSIFICATION_COLUMNS: &str = "
+ALTER TABLE puzzles ADD COLUMN material TEXT NOT NULL DEFAULT '';
+ALTER TABLE puzzles ADD COLUMN phase TEXT NOT NULL DEFAULT '';
+ALTER TABLE puzzles ADD COLUMN pieces INTEGER NOT NULL DEFAULT 0;
+";
+
+pub struct PuzzleStore {
+    connection: Connection
+}
+
+impl PuzzleStore {
+    pub fn open(path: &str) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        connection.create_scalar_function(
+            "material_matches",
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |context| Ok(material_matches(&context.get::<String>(0)?, &context.get::<String>(1)?))
+        )?;
+
+        let mut store = Self { connection };
+        let is_classified = store
+            .connection
+            .prepare("SELECT 1 FROM pragma_table_info('puzzles') WHERE name = 'material'")?
+            .exists([])?;
+        if !is_classified {
+            store.connection.execute_batch(CLASSIFICATION_COLUMNS)?;
+            store.classify_all()?;
+        }
+        Ok(store)
+    }
+
+    fn classify_all(&mut self) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut select = transaction.prepare("SELECT id, fen FROM puzzles")?;
+            let mut update = transaction.prepare("UPDATE puzzles SET material = ?2, phase = ?3, pieces = ?4 WHERE id = ?1")?;
+            let rows: Vec<(String, String)> = select.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<_, _>>()?;
+            for (id, fen) in rows {
+                if let Ok(position) = Position::from_fen(&fen) {
+                    update.execute(params![id, position.material_signature(), position.game_phase().name(), position.piece_count()])?;
+                }
+            }
+        }
+        transaction.commit()
+    }
+
+    pub fn count(&self) -> rusqlite::Result<usize> {
+        self.connection.query_row("SELECT COUNT(*) FROM puzzles", [], |row| row.get(0))
+    }
+
+    // Re-importing a newer database updates puzzles in place, keeping which ones have been seen.
+    // Puzzles that fail validation are left out, so searches never have to skip them.
+    // Returns how many puzzles were stored and how many rows were unreadable or invalid
+    pub fn insert_all(
+        &mut self,
+        db_puzzles: impl Iterator<Item = Result<DbPuzzle, Box<dyn Error>>>
+    ) -> rusqlite::Result<(usize, usize)> {
+        let transaction = self.connection.transaction()?;
+        let (mut stored, mut skipped) = (0, 0);
+        {
+            let mut insert_puzzle = transaction.prepare(
+                "INSERT OR REPLACE INTO puzzles (id, fen, solution, rating, popularity, plays, themes, material, phase, pieces)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"
+            )?;
+            let mut clear_themes = transaction.prepare("DELETE FROM puzzle_themes WHERE puzzle_id = ?1")?;
+            let mut clear_openings = transaction.prepare("DELETE FROM puzzle_openings WHERE puzzle_id = ?1")?;
+            let mut insert_theme = transaction.prepare("INSERT OR IGNORE INTO puzzle_themes (puzzle_id, theme) VALUES (?1, ?2)")?;
+            let mut insert_opening = transaction.prepare("INSERT OR IGNORE INTO puzzle_openings (puzzle_id, opening) VALUES (?1, ?2)")?;
+
+            for db_puzzle in db_puzzles {
+                let Some((db_puzzle, position)) = db_puzzle.ok().and_then(|db_puzzle| {
+                    db_puzzle.puzzle.validate().ok()?;
+                    let position = Position::from_fen(&db_puzzle.puzzle.fen).ok()?;
+                    Some((db_puzzle, position))
+                }) else {
+                    skipped += 1;
+                    continue;
+                };
+                let puzzle = &db_puzzle.puzzle;
+                insert_puzzle.execute(params![
+                    puzzle.id,
+                    puzzle.fen,
+                    puzzle.solution.join(" "),
+                    puzzle.rating,
+                    db_puzzle.popularity,
+                    db_puzzle.plays,
+                    puzzle.themes.join(" "),
+                    position.material_signature(),
+                    position.game_phase().name(),
+                    position.piece_count()
+                ])?;
+                clear_themes.execute([&puzzle.id])?;
+                clear_openings.execute([&puzzle.id])?;
+                for theme in &puzzle.themes {
+                    insert_theme.execute([&puzzle.id, theme])?;
+                }
+                for opening in &db_puzzle.openings {
+                    insert_opening.execute([&puzzle.id, opening])?;
+                }
+                stored += 1;
+            }
+        }
+        transaction.commit()?;
+        Ok((stored, skipped))
+    }
+
+    // A random sample of up to `n` matching puzzles
+    pub fn search(&self, filter: &DbFilter, unseen_only: bool, n: usize) -> rusqlite::Result<Vec<Puzzle>> {
+        let mut sql = "SELECT id, fen, solution, rating, themes FROM puzzles WHERE 1".to_string();
+        let mut values: Vec<Value> = Vec::new();
+
+        for theme in &filter.themes {
+            sql.push_str(" AND id IN (SELECT puzzle_id FROM puzzle_themes WHERE theme = ?)");
+            values.push(Value::Text(theme.clone()));
+        }
+        if !filter.openings.is_empty() {
+            let placeholders = vec!["?"; filter.openings.len()].join(", ");
+            sql.push_str(&format!(" AND id IN (SELECT puzzle_id FROM puzzle_openings WHERE opening IN ({}))", placeholders));
+            values.extend(filter.openings.iter().map(|opening| Value::Text(opening.clone())));
+        }
+        let bounds = [
+            ("rating >= ?", filter.min_rating),
+            ("rating <= ?", filter.max_rating),
+            ("popularity >= ?", filter.min_popularity),
+            ("plays >= ?", filter.min_plays)
+        ];
+        for (condition, bound) in bounds {
+            if let Some(bound) = bound {
+                sql.push_str(&format!(" AND {}", condition));
+                values.push(Value::Integer(i64::from(bound)));
+            }
+        }
+        if let Some(pattern) = &filter.position.material {
+            sql.push_str(" AND material_matches(material, ?)");
+            values.push(Value::Text(pattern.clone()));
+        }
+        if let Some(phase) = filter.position.phase {
+            sql.push_str(" AND phase = ?");
+            values.push(Value::Text(phase.name().to_string()));
+        }
+        if let Some(max) = filter.position.max_pieces {
+            sql.push_str(" AND pieces <= ?");
+            values.push(Value::Integer(max as i64));
+        }
+        if unseen_only {
+            sql.push_str(" AND id NOT IN (SELECT id FROM seen)");
+        }
+        sql.push_str(" ORDER BY RANDOM() LIMIT ?");
+        values.push(Value::Integer(n as i64));
+
+        let mut statement = self.connection.prepare(&sql)?;
+        let rows = statement.query_map(params_from_iter(values), |row| {
+            let solution: String = row.get(2)?;
+            let themes: String = row.get(4)?;
+            Ok(Puzzle {
+                id: row.get(0)?,
+                fen: row.get(1)?,
+                solution: solution.split_whitespace().map(str::to_string).collect(),
+                rating: row.get(3)?,
+                themes: themes.split_whitespace().map(str::to_string).collect(),
+                source: PuzzleSource::Database
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn mark_seen(&self, puzzles: &[Puzzle]) -> rusqlite::Result<()> {
+        let mut statement = self.connection.prepare("INSERT OR IGNORE INTO seen (id) VALUES (?1)")?;
+        for puzzle in puzzles {
+            statement.execute([&puzzle.id])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imports::puzzle_db::read_puzzle_db;
+    use crate::notation_utils::material::PositionFilter;
+
+    const CSV: &str = "\
+PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,OpeningTags
+00008,r6k/pp2r2p/4Rp1Q/3p4/8/1N1P2R1/PqP2bPP/7K b - - 0 24,f2g3 e6e7 b2b1 b3c1 b1c1 h6c1,1853,76,94,6790,crushing hangingPiece long middlegame,https://lichess.org/787zsVup/black#48,
+0000D,5rk1/1p3ppp/pq3b2/8/8/1P1Q1N2/P4PPP/3R2K1 w - - 2 27,d3d6 f8d8 d6d8 f6d8,1580,74,96,40233,advantage endgame short,https://lichess.org/F8M8OS71#53,
+0009B,r2qr1k1/b1p2ppp/pp4n1/P1P1p3/4P1n1/B2P2Pb/3NBP1P/RN1QR1K1 b - - 1 16,b6c5 e2g4 h3g4 d1g4,1128,81,87,611,advantage middlegame short,https://lichess.org/4MWQCxQ6/black#32,Kings_Pawn_Game Kings_Pawn_Game_Leonardis_Variation
+bad01,6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1,a1a8,1500,75,90,100,mate,,
+bad02,6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1,g8h8 a1a2,1500,75,90,100,mate mateIn1,,
+";
+
+    fn ids(puzzles: &[Puzzle]) -> Vec<&str> {
+        let mut ids: Vec<&str> = puzzles.iter().map(|puzzle| puzzle.id.as_str()).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_puzzle_store() {
+        let mut store = PuzzleStore::open(":memory:").unwrap();
+        assert_eq!(store.insert_all(read_puzzle_db(CSV.as_bytes())).unwrap(), (3, 2));
+        // Importing again replaces rather than duplicates
+        assert_eq!(store.insert_all(read_puzzle_db(CSV.as_bytes())).unwrap(), (3, 2));
+        assert_eq!(store.count().unwrap(), 3);
+
+        let all = store.search(&DbFilter::default(), false, 64).unwrap();
+        assert_eq!(ids(&all), vec!["00008", "0000D", "0009B"]);
+        let first = all.iter().find(|puzzle| puzzle.id == "00008").unwrap();
+        assert_eq!(first.fen, "r6k/pp2r2p/4Rp1Q/3p4/8/1N1P2b1/PqP3PP/7K w - - 0 25");
+        assert_eq!(first.solution, vec!["e6e7", "b2b1", "b3c1", "b1c1", "h6c1"]);
+        assert_eq!(first.themes, vec!["crushing", "hangingPiece", "long", "middlegame"]);
+        assert_eq!(first.source, PuzzleSource::Database);
+
+        let filter = |filter: DbFilter| ids(&store.search(&filter, false, 64).unwrap()).join(" ");
+        let advantage = vec!["advantage".to_string()];
+        assert_eq!(filter(DbFilter { themes: advantage.clone(), ..Default::default() }), "0000D 0009B");
+        assert_eq!(filter(DbFilter { themes: advantage, min_rating: Some(1200), max_rating: Some(1600), ..Default::default() }), "0000D");
+        assert_eq!(filter(DbFilter { openings: vec!["Kings_Pawn_Game".to_string()], ..Default::default() }), "0009B");
+        assert_eq!(filter(DbFilter { min_popularity: Some(95), min_plays: Some(1000), ..Default::default() }), "0000D");
+        assert_eq!(store.search(&DbFilter::default(), false, 2).unwrap().len(), 2);
+
+        store.mark_seen(&all[..1]).unwrap();
+        assert_eq!(store.search(&DbFilter::default(), true, 64).unwrap().len(), 2);
+
+        let position = |input: &str| DbFilter { position: PositionFilter::parse(input).unwrap(), ..Default::default() };
+        // lichess tags 0000D an endgame, but with a queen and rook each it isn't one by weight
+        assert_eq!(filter(position("endgame")), "");
+        assert_eq!(filter(position("middlegame")), "00008 0000D 0009B");
+        assert_eq!(filter(position("KRRBQvKNQR")), "00008");
+        assert_eq!(filter(position("KQRB*vKQRN*")), "00008 0000D 0009B");
+        assert_eq!(filter(position("18")), "0000D");
+        assert_eq!(filter(position("KQ*vKQ* 19")), "00008 0000D");
+    }
+
+    #[test]
+    fn test_classifies_older_stores() {
+        let path = std::env::temp_dir().join(format!("puzzle_store_test_{}.sqlite3", std::process::id()));
+        let path = path.to_str().unwrap();
+        {
+            let connection = Connection::open(path).unwrap();
+            connection
+                .execute_batch(
+                    "CREATE TABLE puzzles (id TEXT PRIMARY KEY, fen TEXT NOT NULL, solution TEXT NOT NULL, rating INTEGER NOT NULL,
+                     popularity INTEGER NOT NULL, plays INTEGER NOT NULL, themes TEXT NOT NULL);
+                     INSERT INTO puzzles VALUES ('abcde', '6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 40', 'a1a8', 1500, 90, 100, 'mate');"
+                )
+                .unwrap();
+        }
+
+        let store = PuzzleStore::open(path).unwrap();
+        let filter = DbFilter { position: PositionFilter::parse("KRvK endgame").unwrap(), ..Default::default() };
+        assert_eq!(ids(&store.search(&filter, false, 64).unwrap()), vec!["abcde"]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,4 @@
+pub mod svg_diagram;
+pub mod html_worksheet;
+pub mod latex_document;
+pub mod epd_writer;
@@ -0,0 +1,53 @@
+use crate::api_requests::json_objects::Puzzle;
+use crate::notation_utils::fen_error::read_valid_fen;
+use crate::notation_utils::fen_to_pgn::fen_to_pgn;
+use crate::notation_utils::move_notation::PieceLetters;
+use crate::notation_utils::notation_error::{NotationError, NotationErrorReason};
+
+// One EPD record: the first four FEN fields, then bm (the first solution move), pv (the whole
+// solution), id, c0 (rating) and c1 (themes), plus hmvc/fmvn so the move counters survive a round trip
+pub fn epd_record(puzzle: &Puzzle) -> Result<String, NotationError> {
+    // Round tripping through Position fills in the counters a shortened FEN leaves out
+    let fen = read_valid_fen(&puzzle.fen)?.to_fen();
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    let sans = fen_to_pgn(puzzle.fen.clone(), puzzle.solution.clone(), PieceLetters::English)?;
+    let best_move = sans
+        .first()
+        .ok_or_else(|| NotationError::new(None, &puzzle.id, NotationErrorReason::EmptySolution))?;
+
+    Ok(format!(
+        "{} bm {}; pv {}; id \"{}\"; c0 \"Rating {}\"; c1 \"{}\"; hmvc {}; fmvn {};",
+        fields[..4].join(" "),
+        best_move,
+        sans.join(" "),
+        puzzle.id,
+        puzzle.formatted_rating(),
+        puzzle.themes.join(" "),
+        fields[4],
+        fields[5]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation_utils::fen_error::FenError;
+
+    #[test]
+    fn test_epd_record() {
+        let puzzle = Puzzle::sample();
+        assert_eq!(
+            epd_record(&puzzle).unwrap(),
+            "r2qk2r/p1pp2pp/1p2p3/2b1Pp2/5PPn/2NQ2K1/PPP4P/R1B2R2 w kq f6 bm exf6; pv exf6 Qxf6 Ne4; \
+             id \"xw2Nb\"; c0 \"Rating 1500\"; c1 \"fork middlegame\"; hmvc 0; fmvn 15;"
+        );
+
+        let short_fen = Puzzle { fen: "6k1/5ppp/8/8/8/8/8/R5K1 w - -".to_string(), solution: vec!["a1a8".to_string()], ..puzzle.clone() };
+        assert!(epd_record(&short_fen).unwrap().ends_with("hmvc 0; fmvn 1;"));
+
+        let error = |puzzle: Puzzle| epd_record(&puzzle).unwrap_err().reason;
+        let no_fields = Puzzle { fen: "6k1/5ppp/8/8/8/8/8/R5K1".to_string(), ..short_fen.clone() };
+        assert_eq!(error(no_fields), NotationErrorReason::InvalidFen(FenError::FieldCount(1)));
+        assert_eq!(error(Puzzle { solution: vec![], ..short_fen }), NotationErrorReason::EmptySolution);
+    }
+}
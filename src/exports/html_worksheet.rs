@@ -0,0 +1,108 @@
+use crate::api_requests::json_objects::Puzzle;
+use crate::notation_utils::fen_error::read_valid_fen;
+use crate::notation_utils::move_notation::{MoveStyle, Notation, PieceLetters};
+use crate::notation_utils::position::PieceColor;
+
+use super::svg_diagram::board_svg;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+h1 { font-size: 1.4em; }
+.grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(240px, 1fr)); gap: 1.5em; }
+.puzzle { break-inside: avoid; }
+.puzzle svg { width: 100%; height: auto; }
+.puzzle h2 { font-size: 1em; margin: 0 0 0.3em; }
+.puzzle p { margin: 0.2em 0; font-size: 0.85em; }
+.answers { page-break-before: always; break-before: page; }
+.answers li { margin: 0.3em 0; }
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn puzzle_cell(number: usize, puzzle: &Puzzle) -> String {
+    let (diagram, to_move) = match read_valid_fen(&puzzle.fen) {
+        Ok(position) => {
+            let highlights = position
+                .before_double_push()
+                .map(|(_, last_move)| vec![last_move.from, last_move.to])
+                .unwrap_or_default();
+            let to_move = if position.turn == PieceColor::White { "White to move" } else { "Black to move" };
+            (board_svg(&position, position.turn, &highlights, &[]), to_move.to_string())
+        }
+        Err(e) => (String::new(), escape(&e.to_string()))
+    };
+
+    format!(
+        "<div class=\"puzzle\"><h2>Puzzle {}</h2>{}<p><strong>{}</strong></p><p>Rating {}</p><p>{}</p></div>\n",
+        number, diagram, to_move, puzzle.formatted_rating(), escape(&puzzle.formatted_themes())
+    )
+}
+
+fn answer_line(number: usize, puzzle: &Puzzle, letters: PieceLetters) -> String {
+    let solution = match puzzle.solution_text(Notation { style: MoveStyle::San, letters }) {
+        Ok(text) => escape(&text),
+        Err(e) => format!("<em>{}</em>", escape(&e.to_string()))
+    };
+    let link = escape(&puzzle.link());
+    if link.starts_with("https://") {
+        format!("<li value=\"{}\">{} <a href=\"{}\">{}</a></li>\n", number, solution, link, link)
+    } else {
+        format!("<li value=\"{}\">{} {}</li>\n", number, solution, link)
+    }
+}
+
+// A single printable page with a diagram grid, then the answer key on a page of its own
+pub fn html_worksheet(puzzles: &[Puzzle], letters: PieceLetters) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Puzzle worksheet</title>\n<style>\n{}</style>\n</head>\n<body>\n\
+         <h1>Puzzle worksheet</h1>\n<div class=\"grid\">\n",
+        STYLE
+    );
+    for (i, puzzle) in puzzles.iter().enumerate() {
+        html.push_str(&puzzle_cell(i + 1, puzzle));
+    }
+    html.push_str("</div>\n<section class=\"answers\">\n<h1>Answers</h1>\n<ol>\n");
+    for (i, puzzle) in puzzles.iter().enumerate() {
+        html.push_str(&answer_line(i + 1, puzzle, letters));
+    }
+    html.push_str("</ol>\n</section>\n</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_requests::json_objects::PuzzleSource;
+
+    #[test]
+    fn test_html_worksheet() {
+        let puzzles = vec![
+            Puzzle::sample(),
+            Puzzle {
+                id: "<bad>".to_string(),
+                rating: 900,
+                fen: "not a fen".to_string(),
+                source: PuzzleSource::History,
+                ..Puzzle::sample()
+            }
+        ];
+
+        let html = html_worksheet(&puzzles, PieceLetters::German);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert_eq!(html.matches("<svg").count(), 1);
+        assert!(html.contains("<h2>Puzzle 1</h2>"));
+        assert!(html.contains("<h2>Puzzle 2</h2>"));
+        assert!(html.contains("White to move"));
+        assert!(html.contains("Rating 1500"));
+        assert!(html.contains("Fork, Middlegame"));
+        assert!(html.contains("<li value=\"1\">15. exf6 Dxf6 16. Se4 "));
+        assert!(html.contains("&lt;bad&gt;"));
+        assert!(!html.contains("<bad>"));
+
+        // The answer key comes after every diagram
+        assert!(html.find("class=\"answers\"").unwrap() > html.rfind("class=\"puzzle\"").unwrap());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::api_requests::json_objects::Puzzle;
+use crate::notation_utils::fen_error::read_valid_fen;
+use crate::notation_utils::move_notation::Notation;
+use crate::notation_utils::position::PieceColor;
+
+const PREAMBLE: &str = "\
+\\documentclass{article}
+\\usepackage[utf8]{inputenc}
+\\usepackage[margin=2cm]{geometry}
+\\usepackage{xskak}
+\\usepackage{chessboard}
+\\setchessboard{boardfontsize=18pt, labelfontsize=7pt, showmover=true}
+\\setlength{\\parindent}{0pt}
+
+\\begin{document}
+";
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+fn puzzle_diagram(number: usize, puzzle: &Puzzle) -> String {
+    let caption = format!("Rating {}\\\\\n{}", puzzle.formatted_rating(), escape(&puzzle.formatted_themes()));
+    match read_valid_fen(&puzzle.fen) {
+        Ok(position) => {
+            let (to_move, inverse) = if position.turn == PieceColor::White {
+                ("White to move", "false")
+            } else {
+                ("Black to move", "true")
+            };
+            format!(
+                "\\begin{{minipage}}[t]{{0.48\\textwidth}}\\centering\n\
+                 \\chessboard[setfen={{{}}}, inverse={}]\\\\\n\
+                 \\textbf{{Puzzle {}}} -- {}\\\\\n{}\n\\end{{minipage}}\n",
+                puzzle.fen, inverse, number, to_move, caption
+            )
+        }
+        Err(e) => format!(
+            "\\begin{{minipage}}[t]{{0.48\\textwidth}}\\centering\n\
+             \\textbf{{Puzzle {}}} -- {}\\\\\n{}\n\\end{{minipage}}\n",
+            number, escape(&e.to_string()), caption
+        )
+    }
+}
+
+// xskak replays \mainline itself, so it gets English SAN from the starting FEN, the same
+// movetext as build_pgn. It can't castle in Chess960, so those solutions are printed as text
+fn puzzle_answer(number: usize, puzzle: &Puzzle) -> String {
+    let solution = match (read_valid_fen(&puzzle.fen), puzzle.solution_text(Notation::default())) {
+        (Ok(position), Ok(text)) if !position.chess960 => {
+            format!("\\newchessgame[setfen={{{}}}]\\mainline{{{}}}", puzzle.fen, text)
+        }
+        (_, Ok(text)) => escape(&text),
+        (_, Err(e)) => format!("\\emph{{{}}}", escape(&e.to_string()))
+    };
+    format!(
+        "\\item[{}.] {} \\hfill \\texttt{{{}}}\n",
+        number, solution, escape(&puzzle.link())
+    )
+}
+
+// Two diagrams to a row with rating and themes underneath, then the solutions on a new page
+pub fn latex_document(puzzles: &[Puzzle]) -> String {
+    let mut tex = PREAMBLE.to_string();
+    tex.push_str("\\section*{Puzzles}\n\n");
+    for (i, puzzle) in puzzles.iter().enumerate() {
+        tex.push_str(&puzzle_diagram(i + 1, puzzle));
+        tex.push_str(if i % 2 == 0 { "\\hfill\n" } else { "\n\\bigskip\n\n" });
+    }
+    tex.push_str("\n\\newpage\n\\section*{Solutions}\n\n\\begin{itemize}\n");
+    for (i, puzzle) in puzzles.iter().enumerate() {
+        tex.push_str(&puzzle_answer(i + 1, puzzle));
+    }
+    tex.push_str("\\end{itemize}\n\n\\end{document}\n");
+    tex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_requests::json_objects::PuzzleSource;
+
+    #[test]
+    fn test_latex_document() {
+        let puzzles = vec![
+            Puzzle::sample(),
+            Puzzle {
+                id: "a_b".to_string(),
+                rating: 0,
+                solution: vec!["h7h6".to_string(), "a1a8".to_string()],
+                themes: vec!["backRankMate".to_string()],
+                fen: "6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 30".to_string(),
+                source: PuzzleSource::Epd
+            }
+        ];
+
+        let tex = latex_document(&puzzles);
+        assert!(tex.starts_with("\\documentclass{article}"));
+        assert!(tex.ends_with("\\end{document}\n"));
+        assert_eq!(tex.matches("\\chessboard[").count(), 2);
+        assert!(tex.contains("\\textbf{Puzzle 1} -- White to move"));
+        assert!(tex.contains("\\textbf{Puzzle 2} -- Black to move"));
+        assert!(tex.contains("inverse=true"));
+        assert!(tex.contains("Rating 1500\\\\\nFork, Middlegame"));
+        assert!(tex.contains("Rating ?\\\\\nBack rank mate"));
+        assert!(tex.contains("\\mainline{15. exf6 Qxf6 16. Ne4}"));
+        assert!(tex.contains("\\mainline{30... h6 31. Ra8+}"));
+        assert!(tex.contains("\\texttt{a\\_b}"));
+    }
+
+    #[test]
+    fn test_latex_chess960_answer() {
+        let puzzle = Puzzle {
+            solution: vec!["e1b1".to_string()],
+            fen: "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1".to_string(),
+            ..Puzzle::sample()
+        };
+        let answer = puzzle_answer(1, &puzzle);
+        assert!(!answer.contains("\\mainline"));
+        assert!(answer.contains("1. O-O-O"));
+    }
+}
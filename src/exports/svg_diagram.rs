@@ -0,0 +1,189 @@
+use crate::api_requests::json_objects::Puzzle;
+use crate::notation_utils::fen_error::read_valid_fen;
+use crate::notation_utils::move_notation::{parse_move, solid_figurine, MoveStyle};
+use crate::notation_utils::notation_error::NotationError;
+use crate::notation_utils::position::{PieceColor, Position, Square};
+
+const SQUARE_SIZE: f64 = 45.0;
+const LIGHT_SQUARE: &str = "#f0d9b5";
+const DARK_SQUARE: &str = "#b58863";
+const HIGHLIGHT: &str = "#cdd26a";
+const SOLVER_ARROW: &str = "#15781b";
+const OPPONENT_ARROW: &str = "#882020";
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SvgOptions {
+    pub highlight_last_move: bool,
+    pub solution_arrows: bool
+}
+
+pub struct Arrow {
+    pub from: Square,
+    pub to: Square,
+    pub color: &'static str
+}
+
+// Top left corner of a square, with `perspective`'s pieces at the bottom of the board
+fn corner(square: Square, perspective: PieceColor) -> (f64, f64) {
+    let (column, row) = if perspective == PieceColor::White {
+        (square.1, 7 - square.0)
+    } else {
+        (7 - square.1, square.0)
+    };
+    (column as f64 * SQUARE_SIZE, row as f64 * SQUARE_SIZE)
+}
+
+fn center(square: Square, perspective: PieceColor) -> (f64, f64) {
+    let (x, y) = corner(square, perspective);
+    (x + SQUARE_SIZE / 2.0, y + SQUARE_SIZE / 2.0)
+}
+
+// Drawn as a line and a polygon rather than a marker, so several boards can share one HTML page without id clashes
+fn arrow_svg(arrow: &Arrow, perspective: PieceColor) -> String {
+    let (x1, y1) = center(arrow.from, perspective);
+    let (x2, y2) = center(arrow.to, perspective);
+    let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+    let (dx, dy) = ((x2 - x1) / length, (y2 - y1) / length);
+
+    let head_length = SQUARE_SIZE * 0.4;
+    let head_width = SQUARE_SIZE * 0.3;
+    let (tip_x, tip_y) = (x2 - dx * SQUARE_SIZE * 0.2, y2 - dy * SQUARE_SIZE * 0.2);
+    let (base_x, base_y) = (tip_x - dx * head_length, tip_y - dy * head_length);
+
+    format!(
+        "<g fill=\"{color}\" stroke=\"{color}\" opacity=\"0.8\">\
+         <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke-width=\"{:.1}\" stroke-linecap=\"round\"/>\
+         <polygon points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\" stroke-width=\"1\"/></g>",
+        x1, y1, base_x, base_y, SQUARE_SIZE * 0.15,
+        tip_x, tip_y,
+        base_x - dy * head_width, base_y + dx * head_width,
+        base_x + dy * head_width, base_y - dx * head_width,
+        color = arrow.color
+    )
+}
+
+pub fn board_svg(position: &Position, perspective: PieceColor, highlights: &[Square], arrows: &[Arrow]) -> String {
+    let size = SQUARE_SIZE * 8.0;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {size} {size}\" width=\"{size}\" height=\"{size}\">",
+        size = size
+    );
+
+    for rank in 0..8 {
+        for file in 0..8 {
+            let square = (rank, file);
+            let (x, y) = corner(square, perspective);
+            let is_light = (rank + file) % 2 == 1;
+            let fill = if highlights.contains(&square) {
+                HIGHLIGHT
+            } else if is_light {
+                LIGHT_SQUARE
+            } else {
+                DARK_SQUARE
+            };
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                x, y, SQUARE_SIZE, SQUARE_SIZE, fill
+            ));
+
+            // Coordinates sit in the corners of the left column and bottom row, in the other square colour
+            let label_fill = if is_light { DARK_SQUARE } else { LIGHT_SQUARE };
+            if y == size - SQUARE_SIZE {
+                svg.push_str(&format!(
+                    "<text x=\"{}\" y=\"{}\" font-size=\"10\" font-family=\"sans-serif\" fill=\"{}\">{}</text>",
+                    x + SQUARE_SIZE - 8.0, y + SQUARE_SIZE - 3.0, label_fill, (b'a' + file as u8) as char
+                ));
+            }
+            if x == 0.0 {
+                svg.push_str(&format!(
+                    "<text x=\"2\" y=\"{}\" font-size=\"10\" font-family=\"sans-serif\" fill=\"{}\">{}</text>",
+                    y + 11.0, label_fill, rank + 1
+                ));
+            }
+
+            if let Some(piece) = position.piece_at(square) {
+                let (fill, stroke) = if piece.color == PieceColor::White { ("#fff", "#000") } else { ("#000", "#fff") };
+                svg.push_str(&format!(
+                    "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\" \
+                     fill=\"{}\" stroke=\"{}\" stroke-width=\"0.8\">{}</text>",
+                    x + SQUARE_SIZE / 2.0, y + SQUARE_SIZE / 2.0, SQUARE_SIZE * 0.8, fill, stroke, solid_figurine(piece.name)
+                ));
+            }
+        }
+    }
+
+    for arrow in arrows {
+        svg.push_str(&arrow_svg(arrow, perspective));
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+pub fn puzzle_svg(puzzle: &Puzzle, options: SvgOptions) -> Result<String, NotationError> {
+    let start = read_valid_fen(&puzzle.fen)?;
+
+    let mut highlights = Vec::new();
+    if options.highlight_last_move {
+        if let Some((_, last_move)) = start.before_double_push() {
+            highlights.extend([last_move.from, last_move.to]);
+        }
+    }
+
+    let mut arrows = Vec::new();
+    if options.solution_arrows {
+        let mut position = start.clone();
+        for (i, uci) in puzzle.solution.iter().enumerate() {
+            let mv = parse_move(&position, uci, MoveStyle::Uci).map_err(|e| NotationError::new(Some(i + 1), uci, e.into()))?;
+            let color = if i % 2 == 0 { SOLVER_ARROW } else { OPPONENT_ARROW };
+            arrows.push(Arrow { from: mv.from, to: mv.to, color });
+            position.make_move(&mv);
+        }
+    }
+
+    Ok(board_svg(&start, start.turn, &highlights, &arrows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation_utils::notation_error::NotationErrorReason;
+
+    #[test]
+    fn test_puzzle_svg() {
+        let puzzle = Puzzle::sample();
+
+        let plain = puzzle_svg(&puzzle, SvgOptions::default()).unwrap();
+        assert!(plain.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(plain.ends_with("</svg>"));
+        assert_eq!(plain.matches("<rect").count(), 64);
+        assert_eq!(plain.matches('♟').count(), 15);
+        assert!(!plain.contains(HIGHLIGHT));
+        assert!(!plain.contains("<polygon"));
+
+        let options = SvgOptions { highlight_last_move: true, solution_arrows: true };
+        let annotated = puzzle_svg(&puzzle, options).unwrap();
+        assert_eq!(annotated.matches(HIGHLIGHT).count(), 2);
+        assert_eq!(annotated.matches("<polygon").count(), 3);
+        assert_eq!(annotated.matches(SOLVER_ARROW).count(), 2 * 2);
+
+        let error = |solution: &str| {
+            let puzzle = Puzzle { solution: vec![solution.to_string()], ..puzzle.clone() };
+            puzzle_svg(&puzzle, options).unwrap_err().reason
+        };
+        assert_eq!(error("e5"), NotationErrorReason::Malformed);
+        assert_eq!(error("e5e7"), NotationErrorReason::IllegalMove);
+    }
+
+    #[test]
+    fn test_board_svg_orientation() {
+        let position = Position::from_fen("6k1/8/8/8/8/8/8/R5K1 b - - 0 1").unwrap();
+        assert_eq!(corner((0, 0), PieceColor::White), (0.0, 7.0 * SQUARE_SIZE));
+        assert_eq!(corner((0, 0), PieceColor::Black), (7.0 * SQUARE_SIZE, 0.0));
+
+        // Black to move, so the a1 rook is drawn in the top right corner
+        let svg = board_svg(&position, position.turn, &[], &[]);
+        let rook = format!("x=\"{}\" y=\"{}\"", 7.5 * SQUARE_SIZE, 0.5 * SQUARE_SIZE);
+        assert!(svg.contains(&format!("{} font-size", rook)));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod epd_reader;
+pub mod chess_com_pgn;
+pub mod puzzle_db;
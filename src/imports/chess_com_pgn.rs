@@ -0,0 +1,110 @@
+use std::error::Error;
+
+use crate::api_requests::json_objects::{Puzzle, PuzzleSource};
+use crate::notation_utils::pgn_game::PgnGame;
+use crate::notation_utils::pgn_reader::read_pgn;
+
+// chess.com puts the puzzle's page in Link, and sometimes in Site instead of "Chess.com"
+fn game_link(game: &PgnGame) -> Option<String> {
+    ["Link", "Site"]
+        .into_iter()
+        .filter_map(|name| game.header(name))
+        .find(|value| value.starts_with("https://") || value.starts_with("http://"))
+        .map(str::to_string)
+}
+
+fn game_rating(game: &PgnGame) -> i32 {
+    ["PuzzleRating", "Rating"]
+        .into_iter()
+        .filter_map(|name| game.header(name))
+        .find_map(|value| value.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+fn read_game(game: &PgnGame) -> Result<Puzzle, Box<dyn Error>> {
+    let (start, moves) = game.replay_mainline()?;
+    if moves.is_empty() {
+        return Err(Box::from("there's no solution line"));
+    }
+
+    let mut position = start.clone();
+    let mut solution = Vec::new();
+    for mv in &moves {
+        solution.push(position.to_uci(mv));
+        position.make_move(mv);
+    }
+
+    let link = game_link(game);
+    // The last part of the link is the puzzle's number; without one the position has to do
+    let id = link
+        .as_deref()
+        .and_then(|link| link.trim_end_matches('/').rsplit('/').next())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{:016x}", start.zobrist_hash()));
+
+    Ok(Puzzle {
+        id,
+        rating: game_rating(game),
+        solution,
+        themes: Vec::new(),
+        fen: start.to_fen(),
+        source: PuzzleSource::ChessCom { link }
+    })
+}
+
+// Each game is a puzzle: the FEN header is the position the solver moves from, and the mainline is the solution
+pub fn read_chess_com_pgn(text: &str) -> Result<Vec<Puzzle>, Box<dyn Error>> {
+    read_pgn(text)?
+        .iter()
+        .enumerate()
+        .map(|(i, game)| read_game(game).map_err(|e| format!("Game {} of the PGN: {}", i + 1, e).into()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_chess_com_pgn() {
+        let text = "[Event \"Puzzle\"]\n\
+            [Site \"Chess.com\"]\n\
+            [Result \"*\"]\n\
+            [FEN \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\"]\n\
+            [SetUp \"1\"]\n\
+            [Link \"https://www.chess.com/puzzles/problem/123456\"]\n\
+            [Rating \"1412\"]\n\
+            \n\
+            1. Ra8# *\n\
+            \n\
+            [Event \"Daily Puzzle\"]\n\
+            [Result \"*\"]\n\
+            [FEN \"r2qk2r/p1pp2pp/1p2p3/2b1Pp2/5PPn/2NQ2K1/PPP4P/R1B2R2 w kq f6 0 15\"]\n\
+            \n\
+            15. exf6 Qxf6 16. Ne4 *\n";
+        let puzzles = read_chess_com_pgn(text).unwrap();
+        assert_eq!(puzzles.len(), 2);
+
+        assert_eq!(puzzles[0].id, "123456");
+        assert_eq!(puzzles[0].rating, 1412);
+        assert_eq!(puzzles[0].solution, vec!["a1a8"]);
+        assert_eq!(puzzles[0].fen, "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        assert_eq!(
+            puzzles[0].info_comment(false),
+            "https://www.chess.com/puzzles/problem/123456 (from chess.com)\nRating - 1412\nThemes - "
+        );
+        assert!(puzzles[0].validate().is_ok());
+
+        assert_eq!(puzzles[1].solution, vec!["e5f6", "d8f6", "c3e4"]);
+        assert_eq!(puzzles[1].rating, 0);
+        assert_eq!(puzzles[1].source, PuzzleSource::ChessCom { link: None });
+        assert!(puzzles[1].info_comment(false).contains("(from chess.com)\nRating - ?"));
+    }
+
+    #[test]
+    fn test_read_chess_com_pgn_errors() {
+        let error = |text: &str| read_chess_com_pgn(text).err().unwrap().to_string();
+        assert_eq!(error("[FEN \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\"]\n\n*"), "Game 1 of the PGN: there's no solution line");
+        assert!(error("[FEN \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\"]\n\n1. Rb8 *").starts_with("Game 1 of the PGN: "));
+    }
+}
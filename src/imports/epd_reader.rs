@@ -0,0 +1,159 @@
+use std::error::Error;
+
+use crate::api_requests::json_objects::{Puzzle, PuzzleSource};
+use crate::notation_utils::fen_error::read_valid_fen;
+use crate::notation_utils::san::parse_san;
+
+struct Operation {
+    opcode: String,
+    operands: Vec<String>
+}
+
+// Splits "bm Nf3 Ne5; id \"a; b\";" into operations, keeping quoted operands whole
+fn parse_operations(text: &str) -> Result<Vec<Operation>, String> {
+    let mut operations = Vec::new();
+    let mut tokens: Vec<String> = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => {
+                if !tokens.is_empty() {
+                    let opcode = tokens.remove(0);
+                    operations.push(Operation { opcode, operands: std::mem::take(&mut tokens) });
+                }
+            }
+            '"' => {
+                let mut operand = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => operand.push(c),
+                        None => return Err("unterminated string operand".to_string())
+                    }
+                }
+                tokens.push(operand);
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == ';' || next == '"' {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+                tokens.push(token);
+            }
+        }
+    }
+
+    if !tokens.is_empty() {
+        return Err(format!("operation '{}' is missing its ';'", tokens.join(" ")));
+    }
+    Ok(operations)
+}
+
+fn read_record(line: &str, line_num: usize) -> Result<Puzzle, String> {
+    let position_fields: Vec<&str> = line.split_whitespace().take(4).collect();
+    if position_fields.len() != 4 {
+        return Err("expected a board, side to move, castling rights and en passant square".to_string());
+    }
+    let mut rest = line;
+    for _ in 0..4 {
+        rest = rest.trim_start();
+        rest = rest.find(char::is_whitespace).map_or("", |end| &rest[end..]);
+    }
+    let operations = parse_operations(rest)?;
+    let operand = |opcode: &str| operations.iter().find(|op| op.opcode == opcode).map(|op| &op.operands);
+    let counter = |opcode: &str, default: &str| {
+        operand(opcode).and_then(|operands| operands.first().cloned()).unwrap_or(default.to_string())
+    };
+
+    let fen = format!("{} {} {}", position_fields.join(" "), counter("hmvc", "0"), counter("fmvn", "1"));
+    let mut position = read_valid_fen(&fen).map_err(|e| e.to_string())?;
+
+    // pv is the whole line; bm may list several equally good moves, so only the first is used
+    let sans: Vec<String> = match (operand("pv"), operand("bm")) {
+        (Some(pv), _) if !pv.is_empty() => pv.clone(),
+        (_, Some(bm)) if !bm.is_empty() => vec![bm[0].clone()],
+        _ => return Err("no bm or pv operation".to_string())
+    };
+    let mut solution = Vec::new();
+    for san in &sans {
+        let mv = parse_san(&position, san).map_err(|e| format!("'{}' {}", san, e))?;
+        solution.push(position.to_uci(&mv));
+        position.make_move(&mv);
+    }
+
+    let rating = operand("c0")
+        .and_then(|operands| operands.first())
+        .and_then(|comment| comment.split_whitespace().last())
+        .and_then(|word| word.parse().ok())
+        .unwrap_or(0);
+    let themes = operand("c1")
+        .and_then(|operands| operands.first())
+        .map(|comment| comment.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+    let id = counter("id", &format!("epd-{}", line_num));
+
+    Ok(Puzzle { id, rating, solution, themes, fen, source: PuzzleSource::Epd })
+}
+
+// Reads one record per line, skipping blank lines and '%' comments
+pub fn read_epd(text: &str) -> Result<Vec<Puzzle>, Box<dyn Error>> {
+    let mut puzzles = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('%') {
+            continue;
+        }
+        let puzzle = read_record(line, i + 1).map_err(|e| format!("Line {} of the EPD: {}", i + 1, e))?;
+        puzzles.push(puzzle);
+    }
+    Ok(puzzles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exports::epd_writer::epd_record;
+
+    #[test]
+    fn test_read_epd() {
+        let text = "% Exported puzzles\n\
+            r2qk2r/p1pp2pp/1p2p3/2b1Pp2/5PPn/2NQ2K1/PPP4P/R1B2R2 w kq f6 bm exf6; pv exf6 Qxf6 Ne4; \
+            id \"xw2Nb\"; c0 \"Rating 1500\"; c1 \"fork middlegame\"; hmvc 0; fmvn 15;\n\
+            \n\
+            6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8# Re1; id \"back; rank\";\n";
+        let puzzles = read_epd(text).unwrap();
+        assert_eq!(puzzles.len(), 2);
+
+        assert_eq!(puzzles[0].id, "xw2Nb");
+        assert_eq!(puzzles[0].fen, "r2qk2r/p1pp2pp/1p2p3/2b1Pp2/5PPn/2NQ2K1/PPP4P/R1B2R2 w kq f6 0 15");
+        assert_eq!(puzzles[0].solution, vec!["e5f6", "d8f6", "c3e4"]);
+        assert_eq!(puzzles[0].rating, 1500);
+        assert_eq!(puzzles[0].themes, vec!["fork", "middlegame"]);
+        assert_eq!(epd_record(&puzzles[0]).unwrap(), text.lines().nth(1).unwrap());
+
+        assert_eq!(puzzles[1].id, "back; rank");
+        assert_eq!(puzzles[1].fen, "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        assert_eq!(puzzles[1].solution, vec!["a1a8"]);
+        assert_eq!(puzzles[1].rating, 0);
+        // An unknown rating is written as "?", which reads back as unrated
+        let record = epd_record(&puzzles[1]).unwrap();
+        assert!(record.contains("c0 \"Rating ?\""));
+        assert_eq!(read_epd(&record).unwrap()[0].rating, 0);
+        assert!(puzzles[1].themes.is_empty());
+    }
+
+    #[test]
+    fn test_read_epd_errors() {
+        let error = |text: &str| read_epd(text).err().unwrap().to_string();
+        assert_eq!(error("\n8/8/8/8 w"), "Line 2 of the EPD: expected a board, side to move, castling rights and en passant square");
+        assert_eq!(error("4k3/8/8/8/8/8/8/4K3 w - - id \"x\";"), "Line 1 of the EPD: no bm or pv operation");
+        assert_eq!(error("4k3/8/8/8/8/8/8/4K3 w - - bm Kd2"), "Line 1 of the EPD: operation 'bm Kd2' is missing its ';'");
+        assert!(error("4k3/8/8/8/8/8/8/4K3 w - - bm Ke3;").starts_with("Line 1 of the EPD: 'Ke3' "));
+    }
+}
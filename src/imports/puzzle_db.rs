@@ -0,0 +1,229 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::api_requests::json_objects::{Puzzle, PuzzleSource};
+use crate::notation_utils::fen_error::read_valid_fen;
+use crate::notation_utils::material::PositionFilter;
+use crate::notation_utils::move_notation::{parse_move, MoveStyle};
+use crate::notation_utils::notation_error::{NotationError, NotationErrorReason};
+use crate::notation_utils::position::Position;
+use crate::utils::progress_bar::ProgressReader;
+
+// One row of https://database.lichess.org/#puzzles
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PuzzleRow {
+    puzzle_id: String,
+    #[serde(rename = "FEN")]
+    fen: String,
+    moves: String,
+    rating: i32,
+    popularity: i32,
+    nb_plays: i32,
+    themes: String,
+    opening_tags: String
+}
+
+// A puzzle along with the database columns lichess doesn't send through the API
+#[derive(Clone)]
+pub struct DbPuzzle {
+    pub puzzle: Puzzle,
+    pub popularity: i32,
+    pub plays: i32,
+    pub openings: Vec<String>
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DbFilter {
+    // A puzzle needs every one of these themes, but only one of these openings
+    pub themes: Vec<String>,
+    pub openings: Vec<String>,
+    pub min_rating: Option<i32>,
+    pub max_rating: Option<i32>,
+    pub min_popularity: Option<i32>,
+    pub min_plays: Option<i32>,
+    pub position: PositionFilter
+}
+
+impl DbFilter {
+    pub fn matches(&self, db_puzzle: &DbPuzzle) -> bool {
+        let puzzle = &db_puzzle.puzzle;
+        self.themes.iter().all(|theme| puzzle.themes.contains(theme))
+            && (self.openings.is_empty() || self.openings.iter().any(|opening| db_puzzle.openings.contains(opening)))
+            && self.min_rating.is_none_or(|min| puzzle.rating >= min)
+            && self.max_rating.is_none_or(|max| puzzle.rating <= max)
+            && self.min_popularity.is_none_or(|min| db_puzzle.popularity >= min)
+            && self.min_plays.is_none_or(|min| db_puzzle.plays >= min)
+            && (self.position.is_empty() || Position::from_fen(&puzzle.fen).is_ok_and(|position| self.position.matches(&position)))
+    }
+}
+
+// The database FEN is from before the opponent's move that sets the puzzle up, where the API's is from after it
+fn db_puzzle(row: PuzzleRow) -> Result<DbPuzzle, NotationError> {
+    let mut position = read_valid_fen(&row.fen)?;
+    let mut moves = row.moves.split_whitespace().map(str::to_string);
+    let setup = moves
+        .next()
+        .ok_or_else(|| NotationError::new(None, &row.moves, NotationErrorReason::Malformed))?;
+    let mv = parse_move(&position, &setup, MoveStyle::Uci).map_err(|e| NotationError::new(Some(1), &setup, e.into()))?;
+    position.make_move(&mv);
+
+    Ok(DbPuzzle {
+        puzzle: Puzzle {
+            id: row.puzzle_id,
+            rating: row.rating,
+            solution: moves.collect(),
+            themes: row.themes.split_whitespace().map(str::to_string).collect(),
+            fen: position.to_fen(),
+            source: PuzzleSource::Database
+        },
+        popularity: row.popularity,
+        plays: row.nb_plays,
+        openings: row.opening_tags.split_whitespace().map(str::to_string).collect()
+    })
+}
+
+// Reads lazily, one row at a time, so the whole file never has to fit in memory
+pub fn read_puzzle_db<R: Read>(reader: R) -> impl Iterator<Item = Result<DbPuzzle, Box<dyn Error>>> {
+    csv::Reader::from_reader(reader)
+        .into_deserialize::<PuzzleRow>()
+        .map(|row| {
+            let row = row?;
+            let id = row.puzzle_id.clone();
+            db_puzzle(row).map_err(|e| format!("Puzzle {}: {}", id, e).into())
+        })
+}
+
+// Opens either the plain CSV or the .csv.zst lichess ships, decompressing as it's read rather than
+// unpacking it first. Progress is measured on the file itself, so it's right for both
+pub fn open_puzzle_db(path: &str, show_progress: bool) -> Result<Box<dyn Read>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let total = file.metadata()?.len();
+    let output: Box<dyn Write> = if show_progress { Box::new(io::stdout()) } else { Box::new(io::sink()) };
+    let reader = ProgressReader::new(BufReader::new(file), total, "Reading puzzle database", output);
+
+    if Path::new(path).extension().is_some_and(|extension| extension == "zst") {
+        Ok(Box::new(zstd::Decoder::new(reader)?))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+// Stops reading as soon as it has `n` usable puzzles, so a filter that matches early never scans the whole file
+pub fn find_in_puzzle_db<R: Read>(reader: R, filter: &DbFilter, n: usize, staged: &[Puzzle]) -> Vec<Puzzle> {
+    let mut puzzles: Vec<Puzzle> = Vec::new();
+    let mut total_unreadable: usize = 0;
+
+    for result in read_puzzle_db(reader) {
+        if puzzles.len() >= n {
+            break;
+        }
+        match result {
+            Ok(db_puzzle) if filter.matches(&db_puzzle) => {
+                let puzzle = db_puzzle.puzzle;
+                let is_duplicate = staged.iter().chain(&puzzles).any(|other| puzzle.is_duplicate_of(other));
+                if !is_duplicate && puzzle.validate().is_ok() {
+                    puzzles.push(puzzle);
+                }
+            }
+            Ok(_) => {}
+            Err(_) => total_unreadable += 1
+        }
+    }
+
+    let plural_char = if total_unreadable == 1 { "" } else { "s" };
+    if total_unreadable > 0 { println!("Skipping {} unreadable row{}", total_unreadable, plural_char); }
+
+    puzzles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "\
+PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,OpeningTags
+00008,r6k/pp2r2p/4Rp1Q/3p4/8/1N1P2R1/PqP2bPP/7K b - - 0 24,f2g3 e6e7 b2b1 b3c1 b1c1 h6c1,1853,76,94,6790,crushing hangingPiece long middlegame,https://lichess.org/787zsVup/black#48,
+0000D,5rk1/1p3ppp/pq3b2/8/8/1P1Q1N2/P4PPP/3R2K1 w - - 2 27,d3d6 f8d8 d6d8 f6d8,1580,74,96,40233,advantage endgame short,https://lichess.org/F8M8OS71#53,
+0009B,r2qr1k1/b1p2ppp/pp4n1/P1P1p3/4P1n1/B2P2Pb/3NBP1P/RN1QR1K1 b - - 1 16,b6c5 e2g4 h3g4 d1g4,1128,81,87,611,advantage middlegame short,https://lichess.org/4MWQCxQ6/black#32,Kings_Pawn_Game Kings_Pawn_Game_Leonardis_Variation
+";
+
+    #[test]
+    fn test_read_puzzle_db() {
+        let puzzles: Vec<DbPuzzle> = read_puzzle_db(CSV.as_bytes()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(puzzles.len(), 3);
+
+        let first = &puzzles[0];
+        assert_eq!(first.puzzle.id, "00008");
+        assert_eq!(first.puzzle.fen, "r6k/pp2r2p/4Rp1Q/3p4/8/1N1P2b1/PqP3PP/7K w - - 0 25");
+        assert_eq!(first.puzzle.solution, vec!["e6e7", "b2b1", "b3c1", "b1c1", "h6c1"]);
+        assert_eq!(first.puzzle.themes, vec!["crushing", "hangingPiece", "long", "middlegame"]);
+        assert_eq!((first.puzzle.rating, first.popularity, first.plays), (1853, 94, 6790));
+        assert!(first.openings.is_empty());
+        assert!(first.puzzle.validate().is_ok());
+
+        assert_eq!(puzzles[2].openings, vec!["Kings_Pawn_Game", "Kings_Pawn_Game_Leonardis_Variation"]);
+        assert!(puzzles.iter().all(|db_puzzle| db_puzzle.puzzle.validate().is_ok()));
+    }
+
+    #[test]
+    fn test_read_puzzle_db_errors() {
+        let csv = "PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,OpeningTags\n\
+                   bad01,6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1,a1a8 g8h8,1500,75,90,100,mate,,\n\
+                   bad02,6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1,g8h8,not a number,75,90,100,mate,,\n\
+                   bad03,6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1,g8 g8h8,1500,75,90,100,mate,,\n";
+        let results: Vec<_> = read_puzzle_db(csv.as_bytes()).collect();
+        assert!(results[0].as_ref().err().unwrap().to_string().starts_with("Puzzle bad01: "));
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().err().unwrap().to_string(), "Puzzle bad03: ply 1 'g8' couldn't be read as a move");
+    }
+
+    #[test]
+    fn test_db_filter() {
+        let puzzles: Vec<DbPuzzle> = read_puzzle_db(CSV.as_bytes()).collect::<Result<_, _>>().unwrap();
+        let matching = |filter: DbFilter| -> Vec<String> {
+            puzzles.iter().filter(|p| filter.matches(p)).map(|p| p.puzzle.id.clone()).collect()
+        };
+        let themes = vec!["advantage".to_string()];
+
+        assert_eq!(matching(DbFilter { themes: themes.clone(), min_rating: Some(1200), ..Default::default() }), vec!["0000D"]);
+        assert_eq!(matching(DbFilter { themes: themes.clone(), min_popularity: Some(90), ..Default::default() }), vec!["0000D"]);
+        assert_eq!(matching(DbFilter { min_plays: Some(1000), max_rating: Some(1600), ..Default::default() }), vec!["0000D"]);
+        assert_eq!(matching(DbFilter { openings: vec!["Kings_Pawn_Game".to_string()], ..Default::default() }), vec!["0009B"]);
+        let rook_and_knight = PositionFilter::parse("KQRNvKQRB").unwrap();
+        assert_eq!(matching(DbFilter { position: rook_and_knight, ..Default::default() }), vec!["0000D"]);
+        let endgames = PositionFilter::parse("endgame").unwrap();
+        assert!(matching(DbFilter { position: endgames, ..Default::default() }).is_empty());
+        assert!(puzzles.iter().all(|p| DbFilter::default().matches(p)));
+    }
+
+    #[test]
+    fn test_find_in_puzzle_db() {
+        let found = find_in_puzzle_db(CSV.as_bytes(), &DbFilter::default(), 2, &[]);
+        assert_eq!(found.iter().map(|p| p.id.as_str()).collect::<Vec<_>>(), vec!["00008", "0000D"]);
+
+        let found = find_in_puzzle_db(CSV.as_bytes(), &DbFilter::default(), 64, &found);
+        assert_eq!(found.iter().map(|p| p.id.as_str()).collect::<Vec<_>>(), vec!["0009B"]);
+    }
+
+    #[test]
+    fn test_open_compressed_puzzle_db() {
+        let directory = std::env::temp_dir().join(format!("puzzle_db_test_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let compressed_path = directory.join("lichess_db_puzzle.csv.zst");
+        let plain_path = directory.join("lichess_db_puzzle.csv");
+        std::fs::write(&compressed_path, zstd::encode_all(CSV.as_bytes(), 3).unwrap()).unwrap();
+        std::fs::write(&plain_path, CSV).unwrap();
+
+        for path in [&compressed_path, &plain_path] {
+            let reader = open_puzzle_db(path.to_str().unwrap(), false).unwrap();
+            let found = find_in_puzzle_db(reader, &DbFilter::default(), 64, &[]);
+            assert_eq!(found.len(), 3);
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
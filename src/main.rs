@@ -1,6 +1,7 @@
-// Possible TODO: also allow pasting chess.com puzzle exported pgns into cli as input for convenience
-
 mod api_requests;
+mod database;
+mod exports;
+mod imports;
 mod notation_utils;
 mod utils;
 mod temp_tui;
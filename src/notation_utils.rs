@@ -1,2 +1,14 @@
 pub mod fen_to_pgn;
 pub mod pgn_to_fen;
+pub mod position;
+pub mod san;
+pub mod notation_error;
+pub mod fen_error;
+pub mod pgn_game;
+pub mod pgn_reader;
+pub mod pgn_writer;
+pub mod solution_check;
+pub mod move_notation;
+pub mod zobrist;
+pub mod perft;
+pub mod material;
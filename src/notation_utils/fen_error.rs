@@ -0,0 +1,84 @@
+use std::fmt;
+
+use super::notation_error::NotationError;
+use super::position::{square_to_string, CastlingSide, PieceColor, Position, Square};
+
+// Problems with the text of a FEN, found while parsing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenError {
+    FieldCount(usize),
+    RankCount(usize),
+    // Rank number as written on the board, 8 for the first row of the FEN
+    RankLength(usize),
+    UnknownPiece(char),
+    SideToMove,
+    Castling(char),
+    EnPassant,
+    HalfmoveClock,
+    FullmoveNumber
+}
+
+// Problems with a position that parsed fine but can't come from a legal game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationError {
+    KingCount(PieceColor, usize),
+    PawnOnBackRank(Square),
+    OpponentInCheck,
+    CastlingRights(PieceColor, CastlingSide),
+    EnPassant(Square)
+}
+
+fn color_name(color: PieceColor) -> &'static str {
+    match color {
+        PieceColor::White => "White",
+        PieceColor::Black => "Black"
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::FieldCount(count) => write!(f, "has {} fields instead of 4 to 6", count),
+            FenError::RankCount(count) => write!(f, "has {} ranks instead of 8", count),
+            FenError::RankLength(rank) => write!(f, "rank {} doesn't have exactly 8 squares", rank),
+            FenError::UnknownPiece(c) => write!(f, "'{}' isn't a piece", c),
+            FenError::SideToMove => write!(f, "side to move isn't 'w' or 'b'"),
+            FenError::Castling(c) => write!(f, "'{}' isn't a castling right", c),
+            FenError::EnPassant => write!(f, "en passant field isn't a square or '-'"),
+            FenError::HalfmoveClock => write!(f, "halfmove clock isn't a number"),
+            FenError::FullmoveNumber => write!(f, "fullmove number isn't a number")
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::KingCount(color, count) => {
+                write!(f, "{} has {} kings instead of 1", color_name(*color), count)
+            }
+            ValidationError::PawnOnBackRank(square) => {
+                write!(f, "there's a pawn on {}", square_to_string(*square))
+            }
+            ValidationError::OpponentInCheck => write!(f, "the side not to move is in check"),
+            ValidationError::CastlingRights(color, side) => {
+                let side = if *side == CastlingSide::King { "kingside" } else { "queenside" };
+                write!(f, "{} can castle {} without the king and rook to do it", color_name(*color), side)
+            }
+            ValidationError::EnPassant(square) => {
+                write!(f, "no pawn could have just passed {}", square_to_string(*square))
+            }
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl std::error::Error for ValidationError {}
+
+// Parses and validates in one step, for positions that are about to be played from
+pub fn read_valid_fen(fen: &str) -> Result<Position, NotationError> {
+    let position = Position::from_fen(fen).map_err(|e| NotationError::new(None, fen, e.into()))?;
+    position.validate().map_err(|e| NotationError::new(None, fen, e.into()))?;
+    Ok(position)
+}
@@ -0,0 +1,184 @@
+use super::position::{PieceColor, PieceName, Position};
+
+const SIGNATURE_ORDER: [PieceName; 6] =
+    [PieceName::King, PieceName::Queen, PieceName::Rook, PieceName::Bishop, PieceName::Knight, PieceName::Pawn];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamePhase {
+    Opening,
+    Middlegame,
+    Endgame
+}
+
+impl GamePhase {
+    pub const ALL: [GamePhase; 3] = [GamePhase::Opening, GamePhase::Middlegame, GamePhase::Endgame];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GamePhase::Opening => "Opening",
+            GamePhase::Middlegame => "Middlegame",
+            GamePhase::Endgame => "Endgame"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        GamePhase::ALL.into_iter().find(|phase| phase.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl Position {
+    fn side_signature(&self, color: PieceColor) -> String {
+        let mut signature = String::new();
+        for name in SIGNATURE_ORDER {
+            let count = self.board.iter().flatten().flatten().filter(|piece| piece.name == name && piece.color == color).count();
+            signature.extend(std::iter::repeat_n(name.to_char(), count));
+        }
+        signature
+    }
+
+    // White's pieces then Black's, strongest first, like "KRPvKR"
+    pub fn material_signature(&self) -> String {
+        format!("{}v{}", self.side_signature(PieceColor::White), self.side_signature(PieceColor::Black))
+    }
+
+    pub fn piece_count(&self) -> usize {
+        self.board.iter().flatten().flatten().count()
+    }
+
+    // Weighs the pieces left (minor 1, rook 2, queen 4, out of 24), so a queen or two rooks each
+    // is already an endgame. Anything heavier is an opening for the first ten moves
+    pub fn game_phase(&self) -> GamePhase {
+        let weight: u32 = self
+            .board
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|piece| match piece.name {
+                PieceName::Knight | PieceName::Bishop => 1,
+                PieceName::Rook => 2,
+                PieceName::Queen => 4,
+                PieceName::Pawn | PieceName::King => 0
+            })
+            .sum();
+
+        if weight <= 8 {
+            GamePhase::Endgame
+        } else if self.fullmove_number <= 10 {
+            GamePhase::Opening
+        } else {
+            GamePhase::Middlegame
+        }
+    }
+}
+
+fn sorted_pieces(side: &str) -> Vec<char> {
+    let mut pieces: Vec<char> = side.chars().filter(|&c| c != 'P' && c != '*').collect();
+    pieces.sort();
+    pieces
+}
+
+// Pawns are ignored, and a side ending in '*' may have more pieces than it lists
+fn side_matches(side: &str, pattern: &str) -> bool {
+    let pieces = sorted_pieces(side);
+    let wanted = sorted_pieces(pattern);
+    if !pattern.ends_with('*') {
+        return pieces == wanted;
+    }
+    let mut remaining = pieces;
+    wanted.iter().all(|c| match remaining.iter().position(|piece| piece == c) {
+        Some(i) => {
+            remaining.remove(i);
+            true
+        }
+        None => false
+    })
+}
+
+// "KRvKR" matches every rook endgame, either way round; "KQ*vKQ*" anything with both queens on
+pub fn material_matches(signature: &str, pattern: &str) -> bool {
+    let pattern = pattern.to_uppercase();
+    let (Some((white, black)), Some((first, second))) = (signature.split_once('v'), pattern.split_once('V')) else {
+        return false;
+    };
+    (side_matches(white, first) && side_matches(black, second)) || (side_matches(white, second) && side_matches(black, first))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PositionFilter {
+    pub material: Option<String>,
+    pub phase: Option<GamePhase>,
+    pub max_pieces: Option<usize>
+}
+
+impl PositionFilter {
+    pub fn is_empty(&self) -> bool {
+        self.material.is_none() && self.phase.is_none() && self.max_pieces.is_none()
+    }
+
+    pub fn matches(&self, position: &Position) -> bool {
+        self.material.as_ref().is_none_or(|pattern| material_matches(&position.material_signature(), pattern))
+            && self.phase.is_none_or(|phase| position.game_phase() == phase)
+            && self.max_pieces.is_none_or(|max| position.piece_count() <= max)
+    }
+
+    // Any of "KRvKR", "endgame" and "12", space delimited and in any order
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut filter = PositionFilter::default();
+        for word in input.split_whitespace() {
+            if let Some(phase) = GamePhase::from_name(word) {
+                filter.phase = Some(phase);
+            } else if let Ok(max) = word.parse() {
+                filter.max_pieces = Some(max);
+            } else if word.to_uppercase().matches('V').count() == 1 && word.to_uppercase().chars().all(|c| "KQRBNPV*".contains(c)) {
+                filter.material = Some(word.to_uppercase().replace('V', "v"));
+            } else {
+                return Err(format!("'{}' isn't a material pattern, phase or piece count", word));
+            }
+        }
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classification() {
+        let position = Position::from_fen("8/5pk1/8/8/4P3/8/r7/4K2R w - - 0 50").unwrap();
+        assert_eq!(position.material_signature(), "KRPvKRP");
+        assert_eq!(position.piece_count(), 6);
+        assert_eq!(position.game_phase(), GamePhase::Endgame);
+
+        assert_eq!(Position::default().material_signature(), "KQRRBBNNPPPPPPPPvKQRRBBNNPPPPPPPP");
+        assert_eq!(Position::default().game_phase(), GamePhase::Opening);
+        let middlegame = Position::from_fen("r2qk2r/p1pp2pp/1p2p3/2b1Pp2/5PPn/2NQ2K1/PPP4P/R1B2R2 w kq f6 0 15").unwrap();
+        assert_eq!(middlegame.game_phase(), GamePhase::Middlegame);
+    }
+
+    #[test]
+    fn test_material_matches() {
+        assert!(material_matches("KRPvKRP", "KRvKR"));
+        assert!(material_matches("KRPPvKR", "krvkr"));
+        assert!(!material_matches("KRBvKR", "KRvKR"));
+        assert!(material_matches("KQRBvKQRN", "KQ*vKQ*"));
+        assert!(!material_matches("KQRBvKRN", "KQ*vKQ*"));
+        // Either way round, since the solver can be either colour
+        assert!(material_matches("KRvKBN", "KBNvKR"));
+        assert!(!material_matches("KRvKR", "KRR"));
+    }
+
+    #[test]
+    fn test_position_filter() {
+        let filter = PositionFilter::parse("krvkr* endgame 10").unwrap();
+        assert_eq!(filter.material.as_deref(), Some("KRvKR*"));
+        assert_eq!(filter.phase, Some(GamePhase::Endgame));
+        assert_eq!(filter.max_pieces, Some(10));
+        assert!(PositionFilter::parse("").unwrap().is_empty());
+        assert!(PositionFilter::parse("rooks").is_err());
+
+        let position = Position::from_fen("8/5pk1/8/8/4P3/8/r7/4K2R w - - 0 50").unwrap();
+        assert!(filter.matches(&position));
+        assert!(!PositionFilter { max_pieces: Some(5), ..filter }.matches(&position));
+    }
+}
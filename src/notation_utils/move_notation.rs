@@ -0,0 +1,279 @@
+use super::fen_error::read_valid_fen;
+use super::notation_error::NotationError;
+use super::position::{square_to_string, uci_squares, Move, PieceName, Position};
+use super::san::{check_suffix, move_to_san, parse_san, SanError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoveStyle {
+    // e2e4, e7e8q
+    Uci,
+    // Nf3, exd5, e8=Q+
+    #[default]
+    San,
+    // Ng1-f3, e4xd5, e7-e8=Q+
+    Lan,
+    // ♘f3, exd5, e8=♕+
+    Figurine
+}
+
+impl MoveStyle {
+    pub const ALL: [MoveStyle; 4] = [MoveStyle::Uci, MoveStyle::San, MoveStyle::Lan, MoveStyle::Figurine];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MoveStyle::Uci => "UCI",
+            MoveStyle::San => "SAN",
+            MoveStyle::Lan => "long algebraic",
+            MoveStyle::Figurine => "figurine"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PieceLetters {
+    #[default]
+    English,
+    German,
+    Spanish
+}
+
+impl PieceLetters {
+    pub const ALL: [PieceLetters; 3] = [PieceLetters::English, PieceLetters::German, PieceLetters::Spanish];
+    const PIECES: [PieceName; 6] = [
+        PieceName::Pawn, PieceName::Knight, PieceName::Bishop, PieceName::Rook, PieceName::Queen, PieceName::King
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PieceLetters::English => "English",
+            PieceLetters::German => "German",
+            PieceLetters::Spanish => "Spanish"
+        }
+    }
+
+    pub fn letter(self, piece: PieceName) -> char {
+        match (self, piece) {
+            (PieceLetters::English, _) => piece.to_char(),
+            (PieceLetters::German, PieceName::Pawn) => 'B',
+            (PieceLetters::German, PieceName::Knight) => 'S',
+            (PieceLetters::German, PieceName::Bishop) => 'L',
+            (PieceLetters::German, PieceName::Rook) => 'T',
+            (PieceLetters::German, PieceName::Queen) => 'D',
+            (PieceLetters::German, PieceName::King) => 'K',
+            (PieceLetters::Spanish, PieceName::Pawn) => 'P',
+            (PieceLetters::Spanish, PieceName::Knight) => 'C',
+            (PieceLetters::Spanish, PieceName::Bishop) => 'A',
+            (PieceLetters::Spanish, PieceName::Rook) => 'T',
+            (PieceLetters::Spanish, PieceName::Queen) => 'D',
+            (PieceLetters::Spanish, PieceName::King) => 'R'
+        }
+    }
+
+    // Only uppercase piece letters are swapped, so squares, castling, UCI and figurines pass through
+    fn localize(self, text: &str) -> String {
+        text.chars()
+            .map(|c| match PieceName::from_char(c) {
+                Some(piece) if c.is_ascii_uppercase() => self.letter(piece),
+                _ => c
+            })
+            .collect()
+    }
+
+    fn delocalize(self, text: &str) -> String {
+        text.chars()
+            .map(|c| match Self::PIECES.into_iter().find(|&piece| self.letter(piece) == c) {
+                Some(piece) if piece != PieceName::Pawn => piece.to_char(),
+                _ => c
+            })
+            .collect()
+    }
+}
+
+// How moves are written anywhere a person reads them, as opposed to the movetext lichess parses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Notation {
+    pub style: MoveStyle,
+    pub letters: PieceLetters
+}
+
+impl Notation {
+    pub fn name(self) -> String {
+        match self.letters {
+            PieceLetters::English => self.style.name().to_string(),
+            letters => format!("{} with {} piece letters", self.style.name(), letters.name())
+        }
+    }
+
+    pub fn format(self, position: &Position, mv: &Move) -> String {
+        self.letters.localize(&format_move(position, mv, self.style))
+    }
+
+    pub fn parse(self, position: &Position, text: &str) -> Result<Move, SanError> {
+        parse_move(position, &self.letters.delocalize(text), self.style)
+    }
+}
+
+impl From<MoveStyle> for Notation {
+    fn from(style: MoveStyle) -> Self {
+        Self { style, letters: PieceLetters::English }
+    }
+}
+
+// The same glyphs are used for both colours, as in printed figurine notation
+fn figurine(piece: PieceName) -> char {
+    match piece {
+        PieceName::Pawn => '♙',
+        PieceName::Knight => '♘',
+        PieceName::Bishop => '♗',
+        PieceName::Rook => '♖',
+        PieceName::Queen => '♕',
+        PieceName::King => '♔'
+    }
+}
+
+// Diagrams draw both sides with these and tell them apart by colour, since outlined glyphs vanish on most terminal fonts
+pub(crate) fn solid_figurine(piece: PieceName) -> char {
+    match piece {
+        PieceName::Pawn => '♟',
+        PieceName::Knight => '♞',
+        PieceName::Bishop => '♝',
+        PieceName::Rook => '♜',
+        PieceName::Queen => '♛',
+        PieceName::King => '♚'
+    }
+}
+
+fn piece_from_figurine(c: char) -> Option<PieceName> {
+    match c {
+        '♘' | '♞' => Some(PieceName::Knight),
+        '♗' | '♝' => Some(PieceName::Bishop),
+        '♖' | '♜' => Some(PieceName::Rook),
+        '♕' | '♛' => Some(PieceName::Queen),
+        '♔' | '♚' => Some(PieceName::King),
+        _ => None
+    }
+}
+
+fn move_to_lan(position: &Position, mv: &Move) -> String {
+    if mv.is_castle() {
+        return move_to_san(position, mv);
+    }
+
+    let mut lan = String::new();
+    if mv.piece != PieceName::Pawn {
+        lan.push(mv.piece.to_char());
+    }
+    lan.push_str(&square_to_string(mv.from));
+    lan.push(if mv.captured.is_some() { 'x' } else { '-' });
+    lan.push_str(&square_to_string(mv.to));
+    if let Some(promotion) = mv.promotion {
+        lan.push('=');
+        lan.push(promotion.to_char());
+    }
+    lan.push_str(check_suffix(position, mv));
+    lan
+}
+
+pub fn format_move(position: &Position, mv: &Move, style: MoveStyle) -> String {
+    match style {
+        MoveStyle::Uci => position.to_uci(mv),
+        MoveStyle::San => move_to_san(position, mv),
+        MoveStyle::Lan => move_to_lan(position, mv),
+        MoveStyle::Figurine => move_to_san(position, mv)
+            .chars()
+            .map(|c| match PieceName::from_char(c) {
+                Some(piece) if c.is_ascii_uppercase() && piece != PieceName::Pawn => figurine(piece),
+                _ => c
+            })
+            .collect()
+    }
+}
+
+pub fn parse_move(position: &Position, text: &str, style: MoveStyle) -> Result<Move, SanError> {
+    match style {
+        MoveStyle::Uci if uci_squares(text).is_none() => Err(SanError::Malformed),
+        MoveStyle::Uci => position.parse_uci(text).ok_or(SanError::IllegalMove),
+        MoveStyle::San => parse_san(position, text),
+        // With the dash gone, the origin square is just SAN's fullest disambiguation
+        MoveStyle::Lan if text.starts_with(['O', '0']) => parse_san(position, text),
+        MoveStyle::Lan => parse_san(position, &text.replace('-', "")),
+        MoveStyle::Figurine => {
+            let san: String = text
+                .chars()
+                .map(|c| piece_from_figurine(c).map_or(c, PieceName::to_char))
+                .collect();
+            parse_san(position, &san)
+        }
+    }
+}
+
+// Converts a whole line of moves played from the FEN, reporting the ply of the first bad one
+pub fn convert_line(fen: &str, moves: &[String], from: Notation, to: Notation) -> Result<Vec<String>, NotationError> {
+    let mut position = read_valid_fen(fen)?;
+
+    let mut converted = Vec::new();
+    for (i, text) in moves.iter().enumerate() {
+        let mv = from.parse(&position, text).map_err(|e| NotationError::new(Some(i + 1), text, e.into()))?;
+        converted.push(to.format(&position, &mv));
+        position.make_move(&mv);
+    }
+
+    Ok(converted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_move() {
+        let position = Position::from_fen("r3k3/1P6/8/8/8/8/8/4K1NR w K - 0 1").unwrap();
+        let cases = [
+            ("g1f3", ["g1f3", "Nf3", "Ng1-f3", "♘f3"]),
+            ("b7a8q", ["b7a8q", "bxa8=Q+", "b7xa8=Q+", "bxa8=♕+"]),
+            ("e1d2", ["e1d2", "Kd2", "Ke1-d2", "♔d2"])
+        ];
+        for (uci, expected) in cases {
+            let mv = position.parse_uci(uci).unwrap();
+            for (style, text) in MoveStyle::ALL.into_iter().zip(expected) {
+                assert_eq!(format_move(&position, &mv, style), text);
+                assert_eq!(parse_move(&position, text, style), Ok(mv));
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_move() {
+        let position = Position::default();
+        assert_eq!(parse_move(&position, "e2-e4", MoveStyle::Lan), parse_move(&position, "e4", MoveStyle::San));
+        assert_eq!(parse_move(&position, "♞c3", MoveStyle::Figurine), parse_move(&position, "Nc3", MoveStyle::San));
+        assert_eq!(parse_move(&position, "e2e5", MoveStyle::Uci), Err(SanError::IllegalMove));
+        assert_eq!(parse_move(&position, "e2", MoveStyle::Uci), Err(SanError::Malformed));
+
+        let moves: Vec<String> = ["e4", "e5", "Nf3", "Nc6", "Bb5"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            convert_line("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &moves, MoveStyle::San.into(), MoveStyle::Lan.into()),
+            Ok(["e2-e4", "e7-e5", "Ng1-f3", "Nb8-c6", "Bf1-b5"].iter().map(|s| s.to_string()).collect())
+        );
+    }
+
+    #[test]
+    fn test_piece_letters() {
+        let position = Position::from_fen("r3k3/1P6/8/8/8/8/8/4K1NR w K - 0 1").unwrap();
+        let german = Notation { style: MoveStyle::San, letters: PieceLetters::German };
+        let spanish = Notation { style: MoveStyle::Lan, letters: PieceLetters::Spanish };
+        let cases = [
+            ("g1f3", "Sf3", "Cg1-f3"),
+            ("b7a8q", "bxa8=D+", "b7xa8=D+"),
+            ("e1d2", "Kd2", "Re1-d2"),
+            ("h1h7", "Th7", "Th1-h7")
+        ];
+        for (uci, german_text, spanish_text) in cases {
+            let mv = position.parse_uci(uci).unwrap();
+            assert_eq!(german.format(&position, &mv), german_text);
+            assert_eq!(spanish.format(&position, &mv), spanish_text);
+            assert_eq!(german.parse(&position, german_text), Ok(mv));
+            assert_eq!(spanish.parse(&position, spanish_text), Ok(mv));
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+
+use super::fen_error::{FenError, ValidationError};
+use super::pgn_reader::{PgnError, PgnErrorKind};
+use super::san::SanError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotationErrorReason {
+    InvalidFen(FenError),
+    IllegalPosition(ValidationError),
+    // The PGN around the moves couldn't be read, with the line it happened on
+    Pgn { line: usize, kind: PgnErrorKind },
+    Malformed,
+    IllegalMove,
+    AmbiguousMove,
+    WrongCheckMarker,
+    EmptySolution
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotationError {
+    // 1-based; None when the problem is the starting FEN rather than a move
+    pub ply: Option<usize>,
+    pub token: String,
+    pub reason: NotationErrorReason
+}
+
+impl NotationError {
+    pub fn new(ply: Option<usize>, token: &str, reason: NotationErrorReason) -> Self {
+        Self { ply, token: token.to_string(), reason }
+    }
+}
+
+impl From<SanError> for NotationErrorReason {
+    fn from(error: SanError) -> Self {
+        match error {
+            SanError::Malformed => NotationErrorReason::Malformed,
+            SanError::IllegalMove => NotationErrorReason::IllegalMove,
+            SanError::AmbiguousMove => NotationErrorReason::AmbiguousMove,
+            SanError::WrongCheckMarker => NotationErrorReason::WrongCheckMarker
+        }
+    }
+}
+
+impl From<PgnError> for NotationError {
+    fn from(error: PgnError) -> Self {
+        NotationError::new(None, &error.token, NotationErrorReason::Pgn { line: error.line, kind: error.kind })
+    }
+}
+
+impl From<FenError> for NotationErrorReason {
+    fn from(error: FenError) -> Self {
+        NotationErrorReason::InvalidFen(error)
+    }
+}
+
+impl From<ValidationError> for NotationErrorReason {
+    fn from(error: ValidationError) -> Self {
+        NotationErrorReason::IllegalPosition(error)
+    }
+}
+
+impl fmt::Display for NotationErrorReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            NotationErrorReason::InvalidFen(error) => return write!(f, "isn't a valid FEN: {}", error),
+            NotationErrorReason::IllegalPosition(error) => return write!(f, "isn't a legal position: {}", error),
+            NotationErrorReason::Pgn { line, kind } => return write!(f, "on line {}: {}", line, kind),
+            NotationErrorReason::Malformed => "couldn't be read as a move",
+            NotationErrorReason::IllegalMove => "isn't a legal move in this position",
+            NotationErrorReason::AmbiguousMove => "could be more than one legal move",
+            NotationErrorReason::WrongCheckMarker => "has a check or mate marker that doesn't match the position",
+            NotationErrorReason::EmptySolution => "has no solution moves"
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ply {
+            Some(ply) => write!(f, "ply {} '{}' {}", ply, self.token, self.reason),
+            None => write!(f, "'{}' {}", self.token, self.reason)
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
@@ -0,0 +1,86 @@
+use super::position::Position;
+
+// Counts the leaf nodes of the legal move tree, the usual way to check a move generator
+pub fn perft(position: &mut Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = position.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for mv in moves {
+        let undo = position.make_move(&mv);
+        nodes += perft(position, depth - 1);
+        position.unmake_move(&mv, undo);
+    }
+    nodes
+}
+
+// Perft split by first move, in UCI, so a wrong total can be narrowed down against another engine
+pub fn divide(position: &Position, depth: u32) -> Vec<(String, u64)> {
+    let mut position = position.clone();
+    let mut counts: Vec<(String, u64)> = position
+        .legal_moves()
+        .into_iter()
+        .map(|mv| {
+            let uci = position.to_uci(&mv);
+            let undo = position.make_move(&mv);
+            let nodes = perft(&mut position, depth.saturating_sub(1));
+            position.unmake_move(&mv, undo);
+            (uci, nodes)
+        })
+        .collect();
+    counts.sort();
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perft_of(fen: &str, depth: u32) -> u64 {
+        perft(&mut Position::from_fen(fen).unwrap(), depth)
+    }
+
+    #[test]
+    fn test_perft_reference_positions() {
+        // Initial position
+        assert_eq!(perft_of("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 3), 8902);
+        // Kiwipete
+        assert_eq!(perft_of("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 3), 97862);
+        // Rook endgame with en passant pins along the rank
+        assert_eq!(perft_of("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 4), 43238);
+        // Promotions, including capturing promotions
+        assert_eq!(perft_of("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 3), 9467);
+        assert_eq!(perft_of("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 3), 62379);
+        assert_eq!(perft_of("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", 3), 89890);
+        // Chess960 castling
+        assert_eq!(perft_of("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", 3), 12189);
+    }
+
+    #[test]
+    fn test_perft_edge_cases() {
+        // En passant that would expose the king
+        assert_eq!(perft_of("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", 4), 10138);
+        // En passant that gives check
+        assert_eq!(perft_of("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 4), 13931);
+        // Promoting out of check, and promoting to give check
+        assert_eq!(perft_of("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", 4), 19174);
+        assert_eq!(perft_of("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", 4), 2661);
+        // Discovered check
+        assert_eq!(perft_of("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", 4), 10276);
+    }
+
+    #[test]
+    fn test_divide() {
+        let counts = divide(&Position::default(), 2);
+        assert_eq!(counts.len(), 20);
+        assert!(counts.iter().all(|(_, nodes)| *nodes == 20));
+        assert_eq!(counts[0].0, "a2a3");
+        assert_eq!(counts.iter().map(|(_, nodes)| nodes).sum::<u64>(), 400);
+    }
+}
@@ -0,0 +1,75 @@
+use super::fen_error::read_valid_fen;
+use super::notation_error::NotationError;
+use super::position::{Move, Position};
+use super::san::parse_san;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PgnNode {
+    pub san: String,
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+    // Alternatives to this move, each starting from the position before it
+    pub variations: Vec<PgnLine>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PgnLine {
+    // Comment that comes before the first move of the line
+    pub comment: Option<String>,
+    pub moves: Vec<PgnNode>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnGame {
+    pub headers: Vec<(String, String)>,
+    pub mainline: PgnLine,
+    pub result: String
+}
+
+impl Default for PgnGame {
+    fn default() -> Self {
+        Self { headers: Vec::new(), mainline: PgnLine::default(), result: "*".to_string() }
+    }
+}
+
+impl PgnNode {
+    pub fn new(san: &str) -> Self {
+        Self { san: san.to_string(), ..Default::default() }
+    }
+}
+
+impl PgnGame {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn starting_position(&self) -> Result<Position, NotationError> {
+        let mut position = match self.header("FEN") {
+            Some(fen) => read_valid_fen(fen)?,
+            None => Position::default()
+        };
+        if self.header("Variant").is_some_and(|variant| variant.eq_ignore_ascii_case("chess960")) {
+            position.chess960 = true;
+        }
+        Ok(position)
+    }
+
+    // Resolves every mainline SAN against the position it's played in
+    pub fn replay_mainline(&self) -> Result<(Position, Vec<Move>), NotationError> {
+        let start = self.starting_position()?;
+        let mut position = start.clone();
+        let mut moves = Vec::new();
+
+        for (i, node) in self.mainline.moves.iter().enumerate() {
+            let mv = parse_san(&position, &node.san)
+                .map_err(|e| NotationError::new(Some(i + 1), &node.san, e.into()))?;
+            position.make_move(&mv);
+            moves.push(mv);
+        }
+
+        Ok((start, moves))
+    }
+}
@@ -0,0 +1,357 @@
+use std::fmt;
+
+use super::pgn_game::{PgnGame, PgnLine, PgnNode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgnErrorKind {
+    UnterminatedString,
+    UnterminatedComment,
+    UnbalancedParenthesis,
+    UnexpectedToken
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnError {
+    pub line: usize,
+    pub token: String,
+    pub kind: PgnErrorKind
+}
+
+impl fmt::Display for PgnErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            PgnErrorKind::UnterminatedString => "string is never closed",
+            PgnErrorKind::UnterminatedComment => "comment is never closed",
+            PgnErrorKind::UnbalancedParenthesis => "variation parentheses don't match",
+            PgnErrorKind::UnexpectedToken => "wasn't expected here"
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {} '{}' {}", self.line, self.token, self.kind)
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    TagOpen,
+    TagClose,
+    Str(String),
+    Comment(String),
+    Nag(u8),
+    VariationOpen,
+    VariationClose,
+    MoveNumber,
+    Result(String),
+    Symbol(String)
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::TagOpen => write!(f, "["),
+            Token::TagClose => write!(f, "]"),
+            Token::Str(value) => write!(f, "\"{}\"", value),
+            Token::Comment(comment) => write!(f, "{{{}}}", comment),
+            Token::Nag(nag) => write!(f, "${}", nag),
+            Token::VariationOpen => write!(f, "("),
+            Token::VariationClose => write!(f, ")"),
+            Token::MoveNumber => write!(f, "move number"),
+            Token::Result(result) | Token::Symbol(result) => write!(f, "{}", result)
+        }
+    }
+}
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_+#=:-/!?".contains(c)
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, PgnError> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut line = 1;
+    let mut i = 0;
+
+    let error = |line: usize, token: &str, kind: PgnErrorKind| PgnError { line, token: token.to_string(), kind };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let at_line_start = i == 0 || chars[i - 1] == '\n';
+
+        match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+            _ if c.is_whitespace() => i += 1,
+            // Escaped lines and rest-of-line comments are dropped entirely
+            '%' if at_line_start => {
+                while i < chars.len() && chars[i] != '\n' { i += 1; }
+            }
+            ';' => {
+                while i < chars.len() && chars[i] != '\n' { i += 1; }
+            }
+            '[' => { tokens.push((Token::TagOpen, line)); i += 1; }
+            ']' => { tokens.push((Token::TagClose, line)); i += 1; }
+            '(' => { tokens.push((Token::VariationOpen, line)); i += 1; }
+            ')' => { tokens.push((Token::VariationClose, line)); i += 1; }
+            '*' => { tokens.push((Token::Result("*".to_string()), line)); i += 1; }
+            '.' => i += 1,
+            '"' => {
+                let start_line = line;
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(error(start_line, &value, PgnErrorKind::UnterminatedString)),
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.get(i + 1), Some('"') | Some('\\')) => {
+                            value.push(chars[i + 1]);
+                            i += 1;
+                        }
+                        Some(&other) => {
+                            if other == '\n' { line += 1; }
+                            value.push(other);
+                        }
+                    }
+                    i += 1;
+                }
+                i += 1;
+                tokens.push((Token::Str(value), start_line));
+            }
+            '{' => {
+                let start_line = line;
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(error(start_line, "{", PgnErrorKind::UnterminatedComment)),
+                        Some('}') => break,
+                        Some(&other) => {
+                            if other == '\n' { line += 1; }
+                            value.push(other);
+                        }
+                    }
+                    i += 1;
+                }
+                i += 1;
+                tokens.push((Token::Comment(value.split_whitespace().collect::<Vec<_>>().join(" ")), start_line));
+            }
+            '$' => {
+                let mut digits = String::new();
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    digits.push(chars[i]);
+                    i += 1;
+                }
+                match digits.parse() {
+                    Ok(nag) => tokens.push((Token::Nag(nag), line)),
+                    Err(_) => return Err(error(line, &format!("${}", digits), PgnErrorKind::UnexpectedToken))
+                }
+            }
+            _ if is_symbol_char(c) => {
+                let mut symbol = String::new();
+                while i < chars.len() && is_symbol_char(chars[i]) {
+                    symbol.push(chars[i]);
+                    i += 1;
+                }
+
+                if symbol.chars().all(|c| c.is_ascii_digit()) && chars.get(i) == Some(&'.') {
+                    while chars.get(i) == Some(&'.') { i += 1; }
+                    tokens.push((Token::MoveNumber, line));
+                } else if RESULTS.contains(&symbol.as_str()) {
+                    tokens.push((Token::Result(symbol), line));
+                } else {
+                    tokens.push((Token::Symbol(symbol), line));
+                }
+            }
+            _ => return Err(error(line, &c.to_string(), PgnErrorKind::UnexpectedToken))
+        }
+    }
+
+    Ok(tokens)
+}
+
+// Move suffixes like "!?" are stored the same way as their numeric annotation glyphs
+fn split_suffix_annotation(symbol: &str) -> (&str, Option<u8>) {
+    let san = symbol.trim_end_matches(['!', '?']);
+    let nag = match &symbol[san.len()..] {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None
+    };
+    (san, nag)
+}
+
+fn append_comment(target: &mut Option<String>, comment: String) {
+    match target {
+        Some(existing) => {
+            existing.push(' ');
+            existing.push_str(&comment);
+        }
+        None => *target = Some(comment)
+    }
+}
+
+pub fn read_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let tokens = tokenize(text)?;
+    let mut games = Vec::new();
+    let mut i = 0;
+
+    let error = |(token, line): &(Token, usize), kind: PgnErrorKind| PgnError {
+        line: *line,
+        token: token.to_string(),
+        kind
+    };
+
+    while i < tokens.len() {
+        let mut game = PgnGame::default();
+
+        while let Some((Token::TagOpen, _)) = tokens.get(i) {
+            match (tokens.get(i + 1), tokens.get(i + 2), tokens.get(i + 3)) {
+                (Some((Token::Symbol(name), _)), Some((Token::Str(value), _)), Some((Token::TagClose, _))) => {
+                    game.headers.push((name.clone(), value.clone()));
+                    i += 4;
+                }
+                _ => return Err(error(&tokens[i], PgnErrorKind::UnexpectedToken))
+            }
+        }
+
+        // The innermost open line is always last on the stack
+        let mut lines: Vec<PgnLine> = vec![PgnLine::default()];
+        let mut has_result = false;
+
+        while i < tokens.len() {
+            let depth = lines.len();
+            let line = lines.last_mut().unwrap();
+            match &tokens[i].0 {
+                Token::TagOpen if depth == 1 => break,
+                Token::Symbol(symbol) => {
+                    let (san, nag) = split_suffix_annotation(symbol);
+                    let mut node = PgnNode::new(san);
+                    node.nags.extend(nag);
+                    line.moves.push(node);
+                }
+                Token::MoveNumber => (),
+                Token::Comment(comment) => match line.moves.last_mut() {
+                    Some(node) => append_comment(&mut node.comment, comment.clone()),
+                    None => append_comment(&mut line.comment, comment.clone())
+                },
+                Token::Nag(nag) => match line.moves.last_mut() {
+                    Some(node) => node.nags.push(*nag),
+                    None => return Err(error(&tokens[i], PgnErrorKind::UnexpectedToken))
+                },
+                Token::VariationOpen => {
+                    if line.moves.is_empty() {
+                        return Err(error(&tokens[i], PgnErrorKind::UnexpectedToken));
+                    }
+                    lines.push(PgnLine::default());
+                }
+                Token::VariationClose => {
+                    if lines.len() == 1 {
+                        return Err(error(&tokens[i], PgnErrorKind::UnbalancedParenthesis));
+                    }
+                    let variation = lines.pop().unwrap();
+                    let parent = lines.last_mut().unwrap();
+                    parent.moves.last_mut().unwrap().variations.push(variation);
+                }
+                Token::Result(result) => {
+                    if depth > 1 {
+                        return Err(error(&tokens[i], PgnErrorKind::UnbalancedParenthesis));
+                    }
+                    game.result = result.clone();
+                    has_result = true;
+                    i += 1;
+                    break;
+                }
+                _ => return Err(error(&tokens[i], PgnErrorKind::UnexpectedToken))
+            }
+            i += 1;
+        }
+
+        if lines.len() > 1 {
+            let last = &tokens[tokens.len() - 1];
+            return Err(error(last, PgnErrorKind::UnbalancedParenthesis));
+        }
+
+        game.mainline = lines.pop().unwrap();
+        if has_result || !game.headers.is_empty() || !game.mainline.moves.is_empty() || game.mainline.comment.is_some() {
+            games.push(game);
+        }
+    }
+
+    Ok(games)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_pgn_movetext() {
+        let games = read_pgn("1. e4 e5 {comment} 2. Nf3 $1 (2. Bc4 Nf6 (2... Bc5) 3. d3) 2... Nc6!? *").unwrap();
+        assert_eq!(games.len(), 1);
+
+        let mainline = &games[0].mainline;
+        let sans: Vec<&str> = mainline.moves.iter().map(|node| node.san.as_str()).collect();
+        assert_eq!(sans, vec!["e4", "e5", "Nf3", "Nc6"]);
+        assert_eq!(mainline.moves[1].comment, Some("comment".to_string()));
+        assert_eq!(mainline.moves[2].nags, vec![1]);
+        assert_eq!(mainline.moves[3].nags, vec![5]);
+
+        let variation = &mainline.moves[2].variations[0];
+        assert_eq!(variation.moves.len(), 3);
+        assert_eq!(variation.moves[1].variations[0].moves[0].san, "Bc5");
+        assert_eq!(games[0].result, "*");
+    }
+
+    #[test]
+    fn test_read_pgn_multiple_games() {
+        let text = "[Event \"Puzzle 1\"]\n\
+                    [FEN \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\"]\n\
+                    [Annotator \"Someone \\\"quoted\\\"\"]\n\
+                    \n\
+                    { White to move\n  and win } 1. Ra8# 1-0\n\
+                    \n\
+                    [Event \"Puzzle 2\"]\n\
+                    % escaped line\n\
+                    1. d4 ; rest of line\n\
+                    d5 1/2-1/2\n";
+        let games = read_pgn(text).unwrap();
+        assert_eq!(games.len(), 2);
+
+        assert_eq!(games[0].header("Event"), Some("Puzzle 1"));
+        assert_eq!(games[0].header("Annotator"), Some("Someone \"quoted\""));
+        assert_eq!(games[0].mainline.comment, Some("White to move and win".to_string()));
+        assert_eq!(games[0].result, "1-0");
+        let (start, moves) = games[0].replay_mainline().unwrap();
+        assert_eq!(start.to_fen(), "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        assert_eq!(moves[0].to, (7, 0));
+
+        assert_eq!(games[1].mainline.moves.len(), 2);
+        assert_eq!(games[1].result, "1/2-1/2");
+    }
+
+    #[test]
+    fn test_read_pgn_errors() {
+        assert_eq!(read_pgn("1. e4 (1. d4").unwrap_err().kind, PgnErrorKind::UnbalancedParenthesis);
+        assert_eq!(read_pgn("1. e4 ) e5").unwrap_err().kind, PgnErrorKind::UnbalancedParenthesis);
+        assert_eq!(read_pgn("1. e4 {oops").unwrap_err().kind, PgnErrorKind::UnterminatedComment);
+        assert_eq!(read_pgn("[Event \"x").unwrap_err().kind, PgnErrorKind::UnterminatedString);
+        assert_eq!(read_pgn("[Event]").unwrap_err().kind, PgnErrorKind::UnexpectedToken);
+
+        let error = read_pgn("1. e4\n2. @").unwrap_err();
+        assert_eq!((error.line, error.kind), (2, PgnErrorKind::UnexpectedToken));
+    }
+}
@@ -0,0 +1,185 @@
+use super::pgn_game::{PgnGame, PgnLine};
+use super::position::Position;
+
+const MAX_LINE_LENGTH: usize = 80;
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+enum Word {
+    Text(String),
+    LineBreak
+}
+
+fn escape_tag_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Brace comments can't be escaped, so braces inside them are swapped for parentheses
+fn push_comment(comment: &str, words: &mut Vec<Word>) {
+    words.push(Word::Text("{".to_string()));
+    for (i, segment) in comment.split('\n').enumerate() {
+        if i > 0 {
+            words.push(Word::LineBreak);
+        }
+        for word in segment.split_whitespace() {
+            words.push(Word::Text(word.replace('{', "(").replace('}', ")")));
+        }
+    }
+    words.push(Word::Text("}".to_string()));
+}
+
+// Plies are counted from the start of the game, so even plies are always White's
+fn push_line(line: &PgnLine, first_ply: usize, words: &mut Vec<Word>) {
+    let mut needs_number = true;
+
+    if let Some(comment) = &line.comment {
+        push_comment(comment, words);
+    }
+
+    for (i, node) in line.moves.iter().enumerate() {
+        let ply = first_ply + i;
+        let move_number = ply / 2 + 1;
+
+        if ply.is_multiple_of(2) {
+            words.push(Word::Text(format!("{}.", move_number)));
+        } else if needs_number {
+            words.push(Word::Text(format!("{}...", move_number)));
+        }
+        words.push(Word::Text(node.san.clone()));
+        needs_number = false;
+
+        for nag in &node.nags {
+            words.push(Word::Text(format!("${}", nag)));
+        }
+
+        if let Some(comment) = &node.comment {
+            push_comment(comment, words);
+            needs_number = true;
+        }
+
+        for variation in &node.variations {
+            words.push(Word::Text("(".to_string()));
+            push_line(variation, ply, words);
+            words.push(Word::Text(")".to_string()));
+            needs_number = true;
+        }
+    }
+}
+
+fn wrap_words(words: Vec<Word>) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut previous_word = String::new();
+
+    for word in words {
+        match word {
+            Word::LineBreak => {
+                lines.push(current);
+                current = String::new();
+                previous_word.clear();
+            }
+            Word::Text(text) => {
+                let needs_space = !current.is_empty() && previous_word != "(" && text != ")";
+                if needs_space && current.len() + 1 + text.len() > MAX_LINE_LENGTH {
+                    lines.push(current);
+                    current = String::new();
+                } else if needs_space {
+                    current.push(' ');
+                }
+                current.push_str(&text);
+                previous_word = text;
+            }
+        }
+    }
+    lines.push(current);
+
+    lines.join("\n")
+}
+
+pub fn write_pgn(game: &PgnGame) -> String {
+    let mut output = String::new();
+
+    for tag in SEVEN_TAG_ROSTER {
+        if tag == "Result" {
+            output.push_str(&format!("[Result \"{}\"]\n", escape_tag_value(&game.result)));
+        } else if let Some(value) = game.header(tag) {
+            output.push_str(&format!("[{} \"{}\"]\n", tag, escape_tag_value(value)));
+        }
+    }
+    for (name, value) in &game.headers {
+        if !SEVEN_TAG_ROSTER.contains(&name.as_str()) {
+            output.push_str(&format!("[{} \"{}\"]\n", name, escape_tag_value(value)));
+        }
+    }
+
+    let first_ply = game
+        .header("FEN")
+        .and_then(|fen| Position::from_fen(fen).ok())
+        .map_or(0, |position| position.ply());
+
+    let mut words = Vec::new();
+    push_line(&game.mainline, first_ply, &mut words);
+    words.push(Word::Text(game.result.clone()));
+
+    output.push('\n');
+    output.push_str(&wrap_words(words));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation_utils::pgn_game::PgnNode;
+    use crate::notation_utils::pgn_reader::read_pgn;
+
+    #[test]
+    fn test_write_pgn_move_numbers() {
+        let mut game = PgnGame::default();
+        game.headers.push(("FEN".to_string(), "6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 12".to_string()));
+        game.headers.push(("Event".to_string(), "Puzzle 1".to_string()));
+        game.mainline.comment = Some("Black to move".to_string());
+        game.mainline.moves = vec![PgnNode::new("h6"), PgnNode::new("Ra8+"), PgnNode::new("Kh7")];
+        game.mainline.moves[1].comment = Some("a {braced} comment".to_string());
+
+        assert_eq!(
+            write_pgn(&game),
+            "[Event \"Puzzle 1\"]\n\
+             [Result \"*\"]\n\
+             [FEN \"6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 12\"]\n\
+             \n\
+             { Black to move } 12... h6 13. Ra8+ { a (braced) comment } 13... Kh7 *"
+        );
+    }
+
+    #[test]
+    fn test_write_pgn_round_trip() {
+        let text = "[Event \"Test \\\"quoted\\\"\"]\n\
+                    [Result \"1-0\"]\n\
+                    \n\
+                    1. e4 e5 $1 2. Nf3 (2. Bc4 Nf6 (2... Bc5) 3. d3) 2... Nc6 { Line one\n\
+                    line two } 3. Bb5 1-0";
+        let game = read_pgn(text).unwrap().remove(0);
+        let written = write_pgn(&game);
+        assert_eq!(
+            written,
+            "[Event \"Test \\\"quoted\\\"\"]\n\
+             [Result \"1-0\"]\n\
+             \n\
+             1. e4 e5 $1 2. Nf3 (2. Bc4 Nf6 (2... Bc5) 3. d3) 2... Nc6 { Line one line two }\n\
+             3. Bb5 1-0"
+        );
+        assert_eq!(read_pgn(&written).unwrap().remove(0), game);
+    }
+
+    #[test]
+    fn test_write_pgn_line_breaks() {
+        let mut game = PgnGame::default();
+        game.mainline.moves = vec![PgnNode::new("e4")];
+        game.mainline.moves[0].comment = Some("first\nsecond".to_string());
+        assert_eq!(write_pgn(&game), "[Result \"*\"]\n\n1. e4 { first\nsecond } *");
+
+        let long_comment = "word ".repeat(40);
+        game.mainline.moves[0].comment = Some(long_comment);
+        let written = write_pgn(&game);
+        assert!(written.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+    }
+}
@@ -0,0 +1,974 @@
+use super::fen_error::{FenError, ValidationError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PieceName {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PieceColor {
+    White,
+    Black
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Piece {
+    pub name: PieceName,
+    pub color: PieceColor
+}
+
+// (rank, file), so a1 is (0, 0) and h8 is (7, 7)
+pub type Square = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveKind {
+    Normal,
+    DoublePush,
+    EnPassant,
+    Castle { rook_from: Square, rook_to: Square }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub piece: PieceName,
+    pub captured: Option<PieceName>,
+    pub promotion: Option<PieceName>,
+    pub kind: MoveKind
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastlingSide {
+    King,
+    Queen
+}
+
+// Rook files that can still castle, indexed by [color][side]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CastlingRights {
+    rooks: [[Option<usize>; 2]; 2]
+}
+
+// Everything make_move overwrites that can't be recovered from the move itself
+#[derive(Debug, Clone, Copy)]
+pub struct Undo {
+    castling: CastlingRights,
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+    fullmove_number: u32
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    pub board: [[Option<Piece>; 8]; 8],
+    pub turn: PieceColor,
+    pub castling: CastlingRights,
+    pub en_passant: Option<Square>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+    // Castling is written king-takes-rook, and the PGN needs a Chess960 variant header
+    pub chess960: bool
+}
+
+const KNIGHT_DIRECTIONS: [(i32, i32); 8] = [(1, 2), (1, -2), (2, 1), (2, -1), (-1, 2), (-1, -2), (-2, 1), (-2, -1)];
+const KING_DIRECTIONS: [(i32, i32); 8] = [(1, 1), (1, -1), (-1, 1), (-1, -1), (0, 1), (0, -1), (1, 0), (-1, 0)];
+const HV_DIRECTIONS: [(i32, i32); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+const DIAG_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const PROMOTION_PIECES: [PieceName; 4] = [PieceName::Queen, PieceName::Rook, PieceName::Bishop, PieceName::Knight];
+
+impl PieceColor {
+    pub fn opposite(self) -> Self {
+        match self {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            PieceColor::White => 0,
+            PieceColor::Black => 1
+        }
+    }
+
+    pub fn back_rank(self) -> usize {
+        match self {
+            PieceColor::White => 0,
+            PieceColor::Black => 7
+        }
+    }
+
+    fn pawn_direction(self) -> i32 {
+        match self {
+            PieceColor::White => 1,
+            PieceColor::Black => -1
+        }
+    }
+}
+
+impl PieceName {
+    pub fn from_char(c: char) -> Option<Self> {
+        match c.to_ascii_uppercase() {
+            'P' => Some(PieceName::Pawn),
+            'N' => Some(PieceName::Knight),
+            'B' => Some(PieceName::Bishop),
+            'R' => Some(PieceName::Rook),
+            'Q' => Some(PieceName::Queen),
+            'K' => Some(PieceName::King),
+            _ => None
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            PieceName::Pawn => 'P',
+            PieceName::Knight => 'N',
+            PieceName::Bishop => 'B',
+            PieceName::Rook => 'R',
+            PieceName::Queen => 'Q',
+            PieceName::King => 'K'
+        }
+    }
+}
+
+impl Piece {
+    pub fn from_fen_char(c: char) -> Option<Self> {
+        let name = PieceName::from_char(c)?;
+        let color = if c.is_ascii_uppercase() { PieceColor::White } else { PieceColor::Black };
+        Some(Piece { name, color })
+    }
+
+    pub fn to_fen_char(self) -> char {
+        let c = self.name.to_char();
+        if self.color == PieceColor::White { c } else { c.to_ascii_lowercase() }
+    }
+}
+
+impl CastlingSide {
+    fn index(self) -> usize {
+        match self {
+            CastlingSide::King => 0,
+            CastlingSide::Queen => 1
+        }
+    }
+
+    fn king_destination_file(self) -> usize {
+        match self {
+            CastlingSide::King => 6,
+            CastlingSide::Queen => 2
+        }
+    }
+
+    fn rook_destination_file(self) -> usize {
+        match self {
+            CastlingSide::King => 5,
+            CastlingSide::Queen => 3
+        }
+    }
+}
+
+impl CastlingRights {
+    pub fn rook_file(&self, color: PieceColor, side: CastlingSide) -> Option<usize> {
+        self.rooks[color.index()][side.index()]
+    }
+
+    pub fn set(&mut self, color: PieceColor, side: CastlingSide, rook_file: Option<usize>) {
+        self.rooks[color.index()][side.index()] = rook_file;
+    }
+
+    pub fn clear_color(&mut self, color: PieceColor) {
+        self.rooks[color.index()] = [None, None];
+    }
+
+    // Rights that standard chess can't have, with the king off the e-file or a rook off the corners
+    fn is_chess960(&self, board: &[[Option<Piece>; 8]; 8]) -> bool {
+        [PieceColor::White, PieceColor::Black].into_iter().any(|color| {
+            let has_rights = self.rooks[color.index()].iter().any(Option::is_some);
+            has_rights && (back_rank_king_file(board, color) != 4
+                || self.rook_file(color, CastlingSide::King).is_some_and(|file| file != 7)
+                || self.rook_file(color, CastlingSide::Queen).is_some_and(|file| file != 0))
+        })
+    }
+
+    fn clear_rook_square(&mut self, square: Square) {
+        for color in [PieceColor::White, PieceColor::Black] {
+            if square.0 != color.back_rank() { continue; }
+            for side in [CastlingSide::King, CastlingSide::Queen] {
+                if self.rook_file(color, side) == Some(square.1) {
+                    self.set(color, side, None);
+                }
+            }
+        }
+    }
+}
+
+impl Move {
+    pub fn is_castle(&self) -> bool {
+        matches!(self.kind, MoveKind::Castle { .. })
+    }
+}
+
+pub fn square_from_str(s: &str) -> Option<Square> {
+    let mut chars = s.chars();
+    let file = chars.next()?;
+    let rank = chars.next()?;
+    if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+        return None;
+    }
+    Some((rank as usize - '1' as usize, file as usize - 'a' as usize))
+}
+
+pub fn square_to_string(square: Square) -> String {
+    format!("{}{}", (b'a' + square.1 as u8) as char, square.0 + 1)
+}
+
+// Splits a UCI move like e7e8q into its parts without checking it against a position
+pub fn uci_squares(uci: &str) -> Option<(Square, Square, Option<PieceName>)> {
+    if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+        return None;
+    }
+    let from = square_from_str(&uci[0..2])?;
+    let to = square_from_str(&uci[2..4])?;
+    let promotion = match uci[4..].chars().next() {
+        Some(c) => Some(PieceName::from_char(c)?),
+        None => None
+    };
+    Some((from, to, promotion))
+}
+
+fn back_rank_king_file(board: &[[Option<Piece>; 8]; 8], color: PieceColor) -> usize {
+    let king = Some(Piece { name: PieceName::King, color });
+    (0..8).find(|&file| board[color.back_rank()][file] == king).unwrap_or(4)
+}
+
+// The rook X-FEN means by K or Q, or the standard corner when there isn't one
+fn outermost_rook_file(board: &[[Option<Piece>; 8]; 8], color: PieceColor, side: CastlingSide) -> usize {
+    let rook = Some(Piece { name: PieceName::Rook, color });
+    let king_file = back_rank_king_file(board, color);
+    let is_rook = |file: &usize| board[color.back_rank()][*file] == rook;
+    match side {
+        CastlingSide::King => (king_file + 1..8).rev().find(is_rook).unwrap_or(7),
+        CastlingSide::Queen => (0..king_file).find(is_rook).unwrap_or(0)
+    }
+}
+
+fn offset(square: Square, direction: (i32, i32)) -> Option<Square> {
+    let rank = square.0 as i32 + direction.0;
+    let file = square.1 as i32 + direction.1;
+    if (0..8).contains(&rank) && (0..8).contains(&file) {
+        Some((rank as usize, file as usize))
+    } else {
+        None
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap()
+    }
+}
+
+impl Position {
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 || fields.len() > 6 {
+            return Err(FenError::FieldCount(fields.len()));
+        }
+
+        let mut board: [[Option<Piece>; 8]; 8] = [[None; 8]; 8];
+        let rows: Vec<&str> = fields[0].split('/').collect();
+        if rows.len() != 8 {
+            return Err(FenError::RankCount(rows.len()));
+        }
+        for (i, row) in rows.iter().enumerate() {
+            let rank = 7 - i;
+            let mut file = 0;
+            for c in row.chars() {
+                if let Some(digit @ 1..=8) = c.to_digit(10) {
+                    file += digit as usize;
+                } else {
+                    if file >= 8 { return Err(FenError::RankLength(rank + 1)); }
+                    board[rank][file] = Some(Piece::from_fen_char(c).ok_or(FenError::UnknownPiece(c))?);
+                    file += 1;
+                }
+            }
+            if file != 8 {
+                return Err(FenError::RankLength(rank + 1));
+            }
+        }
+
+        let turn = match fields[1] {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            _ => return Err(FenError::SideToMove)
+        };
+
+        let mut castling = CastlingRights::default();
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                let color = if c.is_ascii_uppercase() { PieceColor::White } else { PieceColor::Black };
+                let (side, rook_file) = match c.to_ascii_lowercase() {
+                    'k' => (CastlingSide::King, outermost_rook_file(&board, color, CastlingSide::King)),
+                    'q' => (CastlingSide::Queen, outermost_rook_file(&board, color, CastlingSide::Queen)),
+                    // Shredder-FEN names the rook's file directly
+                    file @ 'a'..='h' => {
+                        let file = file as usize - 'a' as usize;
+                        let side = if file > back_rank_king_file(&board, color) { CastlingSide::King } else { CastlingSide::Queen };
+                        (side, file)
+                    }
+                    _ => return Err(FenError::Castling(c))
+                };
+                castling.set(color, side, Some(rook_file));
+            }
+        }
+        let chess960 = castling.is_chess960(&board);
+
+        let en_passant = if fields[3] == "-" {
+            None
+        } else {
+            Some(square_from_str(fields[3]).ok_or(FenError::EnPassant)?)
+        };
+
+        let halfmove_clock = match fields.get(4) {
+            Some(field) => field.parse().map_err(|_| FenError::HalfmoveClock)?,
+            None => 0
+        };
+        let fullmove_number = match fields.get(5) {
+            Some(field) => field.parse().map_err(|_| FenError::FullmoveNumber)?,
+            None => 1
+        };
+
+        Ok(Self { board, turn, castling, en_passant, halfmove_clock, fullmove_number, chess960 })
+    }
+
+    // Checks the things a FEN can say that no legal game could reach
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        for color in [PieceColor::White, PieceColor::Black] {
+            let king = Some(Piece { name: PieceName::King, color });
+            let kings = self.board.iter().flatten().filter(|&&piece| piece == king).count();
+            if kings != 1 {
+                return Err(ValidationError::KingCount(color, kings));
+            }
+        }
+
+        for rank in [0, 7] {
+            for file in 0..8 {
+                if self.board[rank][file].is_some_and(|piece| piece.name == PieceName::Pawn) {
+                    return Err(ValidationError::PawnOnBackRank((rank, file)));
+                }
+            }
+        }
+
+        if self.is_in_check(self.turn.opposite()) {
+            return Err(ValidationError::OpponentInCheck);
+        }
+
+        for color in [PieceColor::White, PieceColor::Black] {
+            let back_rank = color.back_rank();
+            let king = self.king_square(color);
+            for side in [CastlingSide::King, CastlingSide::Queen] {
+                let Some(rook_file) = self.castling.rook_file(color, side) else { continue };
+                let rook_in_place = self.piece_at((back_rank, rook_file)) == Some(Piece { name: PieceName::Rook, color });
+                let king_in_place = king.is_some_and(|(rank, file)| {
+                    rank == back_rank && if side == CastlingSide::King { file < rook_file } else { file > rook_file }
+                });
+                if !rook_in_place || !king_in_place {
+                    return Err(ValidationError::CastlingRights(color, side));
+                }
+            }
+        }
+
+        if let Some(square) = self.en_passant {
+            // The pawn that just moved belongs to the side not to move
+            let mover = self.turn.opposite();
+            let direction = mover.pawn_direction();
+            let passed_rank = mover.back_rank() as i32 + 2 * direction;
+            let plausible = square.0 as i32 == passed_rank
+                && self.piece_at(square).is_none()
+                && offset(square, (-direction, 0)).is_some_and(|start| self.piece_at(start).is_none())
+                && offset(square, (direction, 0))
+                    .is_some_and(|pawn| self.piece_at(pawn) == Some(Piece { name: PieceName::Pawn, color: mover }));
+            if !plausible {
+                return Err(ValidationError::EnPassant(square));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn placement_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for row in self.board.iter().rev() {
+            let mut empty_count = 0;
+
+            for &cell in row {
+                match cell {
+                    Some(piece) => {
+                        if empty_count > 0 {
+                            fen.push_str(&empty_count.to_string());
+                            empty_count = 0;
+                        }
+                        fen.push(piece.to_fen_char());
+                    }
+                    None => {
+                        empty_count += 1;
+                    }
+                }
+            }
+
+            if empty_count > 0 {
+                fen.push_str(&empty_count.to_string());
+            }
+
+            fen.push('/');
+        }
+        fen.pop();
+
+        fen
+    }
+
+    // Plies played since the start of the game, counting from 0, going by the fullmove number
+    pub fn ply(&self) -> usize {
+        let black_to_move = usize::from(self.turn == PieceColor::Black);
+        (self.fullmove_number.max(1) as usize - 1) * 2 + black_to_move
+    }
+
+    pub fn to_fen(&self) -> String {
+        let move_char = if self.turn == PieceColor::White { 'w' } else { 'b' };
+        let en_passant = match self.en_passant {
+            Some(square) => square_to_string(square),
+            None => "-".to_string()
+        };
+
+        format!(
+            "{} {} {} {} {} {}",
+            self.placement_fen(), move_char, self.castling_fen(), en_passant, self.halfmove_clock, self.fullmove_number
+        )
+    }
+
+    // X-FEN: KQkq when the right belongs to the outermost rook, the rook's file otherwise
+    pub fn castling_fen(&self) -> String {
+        let mut fen = String::new();
+        for color in [PieceColor::White, PieceColor::Black] {
+            for side in [CastlingSide::King, CastlingSide::Queen] {
+                let Some(rook_file) = self.castling.rook_file(color, side) else { continue };
+                let c = if rook_file == outermost_rook_file(&self.board, color, side) {
+                    if side == CastlingSide::King { 'k' } else { 'q' }
+                } else {
+                    (b'a' + rook_file as u8) as char
+                };
+                fen.push(if color == PieceColor::White { c.to_ascii_uppercase() } else { c });
+            }
+        }
+        if fen.is_empty() { "-".to_string() } else { fen }
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.board[square.0][square.1]
+    }
+
+    pub fn king_square(&self, color: PieceColor) -> Option<Square> {
+        let king = Piece { name: PieceName::King, color };
+        (0..8)
+            .flat_map(|rank| (0..8).map(move |file| (rank, file)))
+            .find(|&square| self.piece_at(square) == Some(king))
+    }
+
+    pub fn is_square_attacked(&self, square: Square, by: PieceColor) -> bool {
+        let is_attacker = |target: Option<Square>, names: &[PieceName]| {
+            target
+                .and_then(|target| self.piece_at(target))
+                .is_some_and(|piece| piece.color == by && names.contains(&piece.name))
+        };
+
+        let pawn_rank_offset = -by.pawn_direction();
+        if is_attacker(offset(square, (pawn_rank_offset, 1)), &[PieceName::Pawn])
+            || is_attacker(offset(square, (pawn_rank_offset, -1)), &[PieceName::Pawn])
+        {
+            return true;
+        }
+
+        if KNIGHT_DIRECTIONS.iter().any(|&direction| is_attacker(offset(square, direction), &[PieceName::Knight])) {
+            return true;
+        }
+
+        if KING_DIRECTIONS.iter().any(|&direction| is_attacker(offset(square, direction), &[PieceName::King])) {
+            return true;
+        }
+
+        self.is_attacked_by_slider(square, by, &HV_DIRECTIONS, PieceName::Rook)
+            || self.is_attacked_by_slider(square, by, &DIAG_DIRECTIONS, PieceName::Bishop)
+    }
+
+    // Queens are checked alongside both rooks and bishops
+    fn is_attacked_by_slider(&self, square: Square, by: PieceColor, directions: &[(i32, i32)], name: PieceName) -> bool {
+        for &direction in directions {
+            let mut current = offset(square, direction);
+            while let Some(target) = current {
+                if let Some(piece) = self.piece_at(target) {
+                    if piece.color == by && (piece.name == name || piece.name == PieceName::Queen) {
+                        return true;
+                    }
+                    break;
+                }
+                current = offset(target, direction);
+            }
+        }
+        false
+    }
+
+    pub fn is_in_check(&self, color: PieceColor) -> bool {
+        match self.king_square(color) {
+            Some(square) => self.is_square_attacked(square, color.opposite()),
+            None => false
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut scratch = self.clone();
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|mv| {
+                let undo = scratch.make_move(mv);
+                let is_legal = !scratch.is_in_check(self.turn);
+                scratch.unmake_move(mv, undo);
+                is_legal
+            })
+            .collect()
+    }
+
+    // Rebuilds the position before the double push that left the en passant square, along with the push itself
+    pub fn before_double_push(&self) -> Option<(Position, Move)> {
+        let square = self.en_passant?;
+        let mover = self.turn.opposite();
+        let from = offset(square, (-mover.pawn_direction(), 0))?;
+        let to = offset(square, (mover.pawn_direction(), 0))?;
+
+        let mut before = self.clone();
+        before.board[from.0][from.1] = before.board[to.0][to.1].take();
+        before.turn = mover;
+        before.en_passant = None;
+        if mover == PieceColor::Black {
+            before.fullmove_number = before.fullmove_number.saturating_sub(1).max(1);
+        }
+
+        let mv = before.find_move(from, to, None)?;
+        Some((before, mv))
+    }
+
+    // Matches a legal move by its squares, which is all UCI gives us.
+    // In Chess960 a king step can land where castling would, so the plain move wins
+    pub fn find_move(&self, from: Square, to: Square, promotion: Option<PieceName>) -> Option<Move> {
+        self.legal_moves()
+            .into_iter()
+            .filter(|mv| mv.from == from && mv.to == to && mv.promotion == promotion)
+            .min_by_key(|mv| mv.is_castle())
+    }
+
+    // Accepts castling written king-takes-rook as well as by the king's destination
+    pub fn parse_uci(&self, uci: &str) -> Option<Move> {
+        let (from, to, promotion) = uci_squares(uci)?;
+        let king_takes_rook = self.legal_moves().into_iter().find(|mv| {
+            matches!(mv.kind, MoveKind::Castle { rook_from, .. } if mv.from == from && rook_from == to)
+        });
+        king_takes_rook.or_else(|| self.find_move(from, to, promotion))
+    }
+
+    pub fn to_uci(&self, mv: &Move) -> String {
+        let to = match mv.kind {
+            MoveKind::Castle { rook_from, .. } if self.chess960 => rook_from,
+            _ => mv.to
+        };
+        let mut uci = format!("{}{}", square_to_string(mv.from), square_to_string(to));
+        if let Some(promotion) = mv.promotion {
+            uci.push(promotion.to_char().to_ascii_lowercase());
+        }
+        uci
+    }
+
+    fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+
+        for rank in 0..8 {
+            for file in 0..8 {
+                let from = (rank, file);
+                let piece = match self.piece_at(from) {
+                    Some(piece) if piece.color == self.turn => piece,
+                    _ => continue
+                };
+
+                match piece.name {
+                    PieceName::Pawn => self.pawn_moves(from, &mut moves),
+                    PieceName::Knight => self.step_moves(from, piece.name, &KNIGHT_DIRECTIONS, &mut moves),
+                    PieceName::King => {
+                        self.step_moves(from, piece.name, &KING_DIRECTIONS, &mut moves);
+                        self.castling_moves(from, &mut moves);
+                    }
+                    PieceName::Bishop => self.slide_moves(from, piece.name, &DIAG_DIRECTIONS, &mut moves),
+                    PieceName::Rook => self.slide_moves(from, piece.name, &HV_DIRECTIONS, &mut moves),
+                    PieceName::Queen => {
+                        self.slide_moves(from, piece.name, &DIAG_DIRECTIONS, &mut moves);
+                        self.slide_moves(from, piece.name, &HV_DIRECTIONS, &mut moves);
+                    }
+                }
+            }
+        }
+
+        moves
+    }
+
+    fn push_move(&self, from: Square, to: Square, piece: PieceName, moves: &mut Vec<Move>) {
+        let captured = self.piece_at(to).map(|target| target.name);
+        moves.push(Move { from, to, piece, captured, promotion: None, kind: MoveKind::Normal });
+    }
+
+    fn step_moves(&self, from: Square, piece: PieceName, directions: &[(i32, i32)], moves: &mut Vec<Move>) {
+        for &direction in directions {
+            if let Some(to) = offset(from, direction) {
+                match self.piece_at(to) {
+                    Some(target) if target.color == self.turn => (),
+                    _ => self.push_move(from, to, piece, moves)
+                }
+            }
+        }
+    }
+
+    fn slide_moves(&self, from: Square, piece: PieceName, directions: &[(i32, i32)], moves: &mut Vec<Move>) {
+        for &direction in directions {
+            let mut current = offset(from, direction);
+            while let Some(to) = current {
+                match self.piece_at(to) {
+                    None => self.push_move(from, to, piece, moves),
+                    Some(target) => {
+                        if target.color != self.turn {
+                            self.push_move(from, to, piece, moves);
+                        }
+                        break;
+                    }
+                }
+                current = offset(to, direction);
+            }
+        }
+    }
+
+    fn pawn_moves(&self, from: Square, moves: &mut Vec<Move>) {
+        let direction = self.turn.pawn_direction();
+        let promotion_rank = self.turn.opposite().back_rank();
+        let start_rank = if self.turn == PieceColor::White { 1 } else { 6 };
+
+        let mut push_pawn_move = |to: Square, captured: Option<PieceName>, kind: MoveKind| {
+            if to.0 == promotion_rank {
+                for promotion in PROMOTION_PIECES {
+                    moves.push(Move { from, to, piece: PieceName::Pawn, captured, promotion: Some(promotion), kind });
+                }
+            } else {
+                moves.push(Move { from, to, piece: PieceName::Pawn, captured, promotion: None, kind });
+            }
+        };
+
+        if let Some(to) = offset(from, (direction, 0)) {
+            if self.piece_at(to).is_none() {
+                push_pawn_move(to, None, MoveKind::Normal);
+                if from.0 == start_rank {
+                    if let Some(double_to) = offset(to, (direction, 0)) {
+                        if self.piece_at(double_to).is_none() {
+                            push_pawn_move(double_to, None, MoveKind::DoublePush);
+                        }
+                    }
+                }
+            }
+        }
+
+        for file_direction in [-1, 1] {
+            if let Some(to) = offset(from, (direction, file_direction)) {
+                match self.piece_at(to) {
+                    Some(target) if target.color != self.turn => {
+                        push_pawn_move(to, Some(target.name), MoveKind::Normal);
+                    }
+                    None if self.en_passant == Some(to) => {
+                        let victim = self.piece_at((from.0, to.1));
+                        if victim == Some(Piece { name: PieceName::Pawn, color: self.turn.opposite() }) {
+                            push_pawn_move(to, Some(PieceName::Pawn), MoveKind::EnPassant);
+                        }
+                    }
+                    _ => ()
+                }
+            }
+        }
+    }
+
+    fn castling_moves(&self, king_from: Square, moves: &mut Vec<Move>) {
+        let back_rank = self.turn.back_rank();
+        if king_from.0 != back_rank || self.is_in_check(self.turn) {
+            return;
+        }
+
+        for side in [CastlingSide::King, CastlingSide::Queen] {
+            let rook_file = match self.castling.rook_file(self.turn, side) {
+                Some(file) => file,
+                None => continue
+            };
+            let rook_from = (back_rank, rook_file);
+            if self.piece_at(rook_from) != Some(Piece { name: PieceName::Rook, color: self.turn }) {
+                continue;
+            }
+
+            let king_to = (back_rank, side.king_destination_file());
+            let rook_to = (back_rank, side.rook_destination_file());
+
+            let files = [king_from.1, king_to.1, rook_from.1, rook_to.1];
+            let lowest = *files.iter().min().unwrap();
+            let highest = *files.iter().max().unwrap();
+            let path_is_clear = (lowest..=highest)
+                .filter(|&file| file != king_from.1 && file != rook_from.1)
+                .all(|file| self.piece_at((back_rank, file)).is_none());
+            if !path_is_clear {
+                continue;
+            }
+
+            let king_path_is_safe = (king_from.1.min(king_to.1)..=king_from.1.max(king_to.1))
+                .all(|file| !self.is_square_attacked((back_rank, file), self.turn.opposite()));
+            if !king_path_is_safe {
+                continue;
+            }
+
+            moves.push(Move {
+                from: king_from,
+                to: king_to,
+                piece: PieceName::King,
+                captured: None,
+                promotion: None,
+                kind: MoveKind::Castle { rook_from, rook_to }
+            });
+        }
+    }
+
+    pub fn make_move(&mut self, mv: &Move) -> Undo {
+        let undo = Undo {
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number
+        };
+        let color = self.turn;
+
+        match mv.kind {
+            MoveKind::Castle { rook_from, rook_to } => {
+                self.board[mv.from.0][mv.from.1] = None;
+                self.board[rook_from.0][rook_from.1] = None;
+                self.board[mv.to.0][mv.to.1] = Some(Piece { name: PieceName::King, color });
+                self.board[rook_to.0][rook_to.1] = Some(Piece { name: PieceName::Rook, color });
+            }
+            _ => {
+                if mv.kind == MoveKind::EnPassant {
+                    self.board[mv.from.0][mv.to.1] = None;
+                }
+                let name = mv.promotion.unwrap_or(mv.piece);
+                self.board[mv.from.0][mv.from.1] = None;
+                self.board[mv.to.0][mv.to.1] = Some(Piece { name, color });
+            }
+        }
+
+        self.en_passant = if mv.kind == MoveKind::DoublePush {
+            Some(((mv.from.0 + mv.to.0) / 2, mv.from.1))
+        } else {
+            None
+        };
+
+        if mv.piece == PieceName::King {
+            self.castling.clear_color(color);
+        }
+        self.castling.clear_rook_square(mv.from);
+        self.castling.clear_rook_square(mv.to);
+
+        if mv.piece == PieceName::Pawn || mv.captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if color == PieceColor::Black {
+            self.fullmove_number += 1;
+        }
+
+        self.turn = color.opposite();
+        undo
+    }
+
+    pub fn unmake_move(&mut self, mv: &Move, undo: Undo) {
+        let color = self.turn.opposite();
+
+        match mv.kind {
+            MoveKind::Castle { rook_from, rook_to } => {
+                self.board[mv.to.0][mv.to.1] = None;
+                self.board[rook_to.0][rook_to.1] = None;
+                self.board[mv.from.0][mv.from.1] = Some(Piece { name: PieceName::King, color });
+                self.board[rook_from.0][rook_from.1] = Some(Piece { name: PieceName::Rook, color });
+            }
+            MoveKind::EnPassant => {
+                self.board[mv.to.0][mv.to.1] = None;
+                self.board[mv.from.0][mv.from.1] = Some(Piece { name: PieceName::Pawn, color });
+                self.board[mv.from.0][mv.to.1] = Some(Piece { name: PieceName::Pawn, color: color.opposite() });
+            }
+            _ => {
+                self.board[mv.from.0][mv.from.1] = Some(Piece { name: mv.piece, color });
+                self.board[mv.to.0][mv.to.1] = mv.captured.map(|name| Piece { name, color: color.opposite() });
+            }
+        }
+
+        self.castling = undo.castling;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.turn = color;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fen_round_trip() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r2qk2r/p1pp2pp/1p2p3/2b1Pp2/5PPn/2NQ2K1/PPP4P/R1B2R2 w kq f6 0 15",
+            "8/5ppp/4p3/8/3PP1PP/3K1k2/8/8 b - - 1 40"
+        ];
+        for fen in fens {
+            assert_eq!(Position::from_fen(fen).unwrap().to_fen(), fen);
+        }
+        assert_eq!(Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1"), Err(FenError::RankCount(7)));
+        assert_eq!(
+            Position::from_fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            Err(FenError::UnknownPiece('9'))
+        );
+        assert_eq!(Position::from_fen("rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), Err(FenError::RankLength(7)));
+        assert_eq!(Position::from_fen("8/8/8/8/8/8/8/8 x - - 0 1"), Err(FenError::SideToMove));
+        assert_eq!(Position::from_fen("8/8/8/8/8/8/8/8 w KX - 0 1"), Err(FenError::Castling('X')));
+        assert_eq!(Position::from_fen("8/8/8/8/8/8/8/8 w - e9 0 1"), Err(FenError::EnPassant));
+        assert_eq!(Position::from_fen("8/8/8/8/8/8/8/8 w - - x 1"), Err(FenError::HalfmoveClock));
+        assert_eq!(Position::from_fen("8/8/8/8/8/8/8/8 w -"), Err(FenError::FieldCount(3)));
+    }
+
+    #[test]
+    fn test_validate() {
+        let validate = |fen: &str| Position::from_fen(fen).unwrap().validate();
+
+        assert_eq!(validate("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), Ok(()));
+        assert_eq!(validate("r2qk2r/p1pp2pp/1p2p3/2b1Pp2/5PPn/2NQ2K1/PPP4P/R1B2R2 w kq f6 0 15"), Ok(()));
+        assert_eq!(validate("8/8/8/8/8/8/8/4K3 w - - 0 1"), Err(ValidationError::KingCount(PieceColor::Black, 0)));
+        assert_eq!(validate("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"), Err(ValidationError::KingCount(PieceColor::White, 2)));
+        assert_eq!(validate("4k2P/8/8/8/8/8/8/4K3 w - - 0 1"), Err(ValidationError::PawnOnBackRank((7, 7))));
+        assert_eq!(validate("4k2R/8/8/8/8/8/8/4K3 w - - 0 1"), Err(ValidationError::OpponentInCheck));
+        assert_eq!(
+            validate("4k3/8/8/8/8/8/8/4K3 w K - 0 1"),
+            Err(ValidationError::CastlingRights(PieceColor::White, CastlingSide::King))
+        );
+        assert_eq!(validate("r3k3/8/8/8/8/8/8/4K3 w q - 0 1"), Ok(()));
+        assert_eq!(
+            validate("4k2r/8/8/8/8/8/8/4K3 w q - 0 1"),
+            Err(ValidationError::CastlingRights(PieceColor::Black, CastlingSide::Queen))
+        );
+        assert_eq!(validate("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1"), Ok(()));
+        assert_eq!(validate("4k3/8/8/3pP3/8/8/8/4K3 w - d3 0 1"), Err(ValidationError::EnPassant((2, 3))));
+        assert_eq!(validate("4k3/3p4/8/3pP3/8/8/8/4K3 w - d6 0 1"), Err(ValidationError::EnPassant((5, 3))));
+    }
+
+    #[test]
+    fn test_legal_move_counts() {
+        assert_eq!(Position::default().legal_moves().len(), 20);
+
+        // Kiwipete
+        let position = Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(position.legal_moves().len(), 48);
+
+        // Pinned knight on e2 can't move at all
+        let position = Position::from_fen("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        assert!(position.legal_moves().iter().all(|mv| mv.piece == PieceName::King));
+
+        // Checkmate
+        let position = Position::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+        assert!(position.is_in_check(PieceColor::White));
+        assert!(position.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_make_unmake_restores_position() {
+        let original = Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let mut position = original.clone();
+        for mv in original.legal_moves() {
+            let undo = position.make_move(&mv);
+            for reply in position.legal_moves() {
+                let reply_undo = position.make_move(&reply);
+                position.unmake_move(&reply, reply_undo);
+            }
+            position.unmake_move(&mv, undo);
+            assert_eq!(position, original);
+        }
+    }
+
+    #[test]
+    fn test_special_moves() {
+        let mut position = Position::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+
+        let en_passant = position.parse_uci("e5d6").unwrap();
+        assert_eq!(en_passant.kind, MoveKind::EnPassant);
+        position.make_move(&en_passant);
+        assert_eq!(position.piece_at((4, 3)), None);
+
+        let castle = position.parse_uci("e8c8").unwrap();
+        assert!(castle.is_castle());
+        position.make_move(&castle);
+        assert_eq!(position.to_fen(), "2kr3r/8/3P4/8/8/8/8/R3K2R w KQ - 1 2");
+
+        let rook_move = position.parse_uci("h1h8").unwrap();
+        position.make_move(&rook_move);
+        assert_eq!(position.castling_fen(), "Q");
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        // Shredder-FEN rights for the outermost rooks read back as X-FEN's KQkq
+        let position = Position::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+        assert!(position.chess960);
+        assert_eq!(position.to_fen(), "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9");
+        assert_eq!(position.legal_moves().len(), 21);
+
+        // An inner rook keeps its file in X-FEN
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/RR3K2 w B - 0 1").unwrap();
+        assert_eq!(position.castling_fen(), "B");
+        assert_eq!(position.validate(), Ok(()));
+
+        let original = Position::from_fen("4k3/8/8/8/8/8/8/R4KR1 w KQ - 0 1").unwrap();
+        assert!(original.chess960);
+
+        let mut position = original.clone();
+        let castle = position.parse_uci("f1g1").unwrap();
+        assert!(castle.is_castle());
+        assert_eq!(position.to_uci(&castle), "f1g1");
+        position.make_move(&castle);
+        assert_eq!(position.to_fen(), "4k3/8/8/8/8/8/8/R4RK1 b - - 1 1");
+
+        let mut position = original.clone();
+        let castle = position.parse_uci("f1a1").unwrap();
+        assert!(castle.is_castle());
+        assert_eq!(castle.to, (0, 2));
+        position.make_move(&castle);
+        assert_eq!(position.to_fen(), "4k3/8/8/8/8/8/8/2KR2R1 b - - 1 1");
+
+        // Standard chess accepts king-takes-rook too, but still writes the king's destination
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert!(!position.chess960);
+        let castle = position.parse_uci("e1h1").unwrap();
+        assert!(castle.is_castle());
+        assert_eq!(position.to_uci(&castle), "e1g1");
+    }
+}
@@ -0,0 +1,238 @@
+use std::fmt;
+
+use super::position::{square_from_str, square_to_string, Move, MoveKind, PieceName, Position};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SanError {
+    Malformed,
+    IllegalMove,
+    AmbiguousMove,
+    WrongCheckMarker
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            SanError::Malformed => "not a valid SAN move",
+            SanError::IllegalMove => "no legal move matches",
+            SanError::AmbiguousMove => "more than one legal move matches",
+            SanError::WrongCheckMarker => "check or mate marker doesn't match the position"
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl std::error::Error for SanError {}
+
+// Resolves a SAN move against the legal moves of the position, so pinned pieces are never picked
+pub fn parse_san(position: &Position, san: &str) -> Result<Move, SanError> {
+    let san = san.trim_end_matches(['!', '?']);
+    let marker = match san.chars().last() {
+        Some(c @ ('+' | '#')) => Some(c),
+        _ => None
+    };
+    let san = san.trim_end_matches(['+', '#']);
+
+    let mv = parse_san_body(position, san)?;
+    if let Some(marker) = marker {
+        if check_suffix(position, &mv) != marker.to_string() {
+            return Err(SanError::WrongCheckMarker);
+        }
+    }
+    Ok(mv)
+}
+
+fn parse_san_body(position: &Position, san: &str) -> Result<Move, SanError> {
+    let castle_file = match san {
+        "O-O" | "0-0" => Some(6),
+        "O-O-O" | "0-0-0" => Some(2),
+        _ => None
+    };
+    if let Some(file) = castle_file {
+        return position.legal_moves()
+            .into_iter()
+            .find(|mv| mv.is_castle() && mv.to.1 == file)
+            .ok_or(SanError::IllegalMove);
+    }
+
+    let mut chars: Vec<char> = san.chars().collect();
+
+    let piece = match chars.first() {
+        Some(&c) if "NBRQK".contains(c) => {
+            chars.remove(0);
+            PieceName::from_char(c).unwrap()
+        }
+        Some(_) => PieceName::Pawn,
+        None => return Err(SanError::Malformed)
+    };
+
+    let mut promotion = None;
+    if let Some(&c) = chars.last() {
+        if "NBRQ".contains(c) {
+            promotion = PieceName::from_char(c);
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+    }
+
+    if chars.len() < 2 {
+        return Err(SanError::Malformed);
+    }
+    let target: String = chars.split_off(chars.len() - 2).into_iter().collect();
+    let to = square_from_str(&target).ok_or(SanError::Malformed)?;
+
+    let is_capture = chars.last() == Some(&'x');
+    if is_capture {
+        chars.pop();
+    }
+
+    let mut from_file = None;
+    let mut from_rank = None;
+    for c in chars {
+        match c {
+            'a'..='h' if from_file.is_none() && from_rank.is_none() => from_file = Some(c as usize - 'a' as usize),
+            '1'..='8' if from_rank.is_none() => from_rank = Some(c as usize - '1' as usize),
+            _ => return Err(SanError::Malformed)
+        }
+    }
+
+    let candidates: Vec<Move> = position.legal_moves()
+        .into_iter()
+        .filter(|mv| {
+            mv.piece == piece
+                && !mv.is_castle()
+                && mv.to == to
+                && mv.promotion == promotion
+                && is_capture == mv.captured.is_some()
+                && from_file.is_none_or(|file| mv.from.1 == file)
+                && from_rank.is_none_or(|rank| mv.from.0 == rank)
+        })
+        .collect();
+
+    match candidates.len() {
+        0 => Err(SanError::IllegalMove),
+        1 => Ok(candidates[0]),
+        _ => Err(SanError::AmbiguousMove)
+    }
+}
+
+pub fn move_to_san(position: &Position, mv: &Move) -> String {
+    if let MoveKind::Castle { rook_from, .. } = mv.kind {
+        return if rook_from.1 > mv.from.1 { "O-O".to_string() } else { "O-O-O".to_string() };
+    }
+
+    let mut san = String::new();
+
+    if mv.piece == PieceName::Pawn {
+        if mv.captured.is_some() {
+            san.push((b'a' + mv.from.1 as u8) as char);
+        }
+    } else {
+        san.push(mv.piece.to_char());
+
+        let rivals: Vec<Move> = position.legal_moves()
+            .into_iter()
+            .filter(|other| other.piece == mv.piece && other.to == mv.to && other.from != mv.from && !other.is_castle())
+            .collect();
+
+        if !rivals.is_empty() {
+            let from = square_to_string(mv.from);
+            if rivals.iter().all(|other| other.from.1 != mv.from.1) {
+                san.push_str(&from[..1]);
+            } else if rivals.iter().all(|other| other.from.0 != mv.from.0) {
+                san.push_str(&from[1..]);
+            } else {
+                san.push_str(&from);
+            }
+        }
+    }
+
+    if mv.captured.is_some() {
+        san.push('x');
+    }
+    san.push_str(&square_to_string(mv.to));
+
+    if let Some(promotion) = mv.promotion {
+        san.push('=');
+        san.push(promotion.to_char());
+    }
+
+    san.push_str(check_suffix(position, mv));
+    san
+}
+
+pub fn check_suffix(position: &Position, mv: &Move) -> &'static str {
+    let mut after = position.clone();
+    after.make_move(mv);
+    if !after.is_in_check(after.turn) {
+        ""
+    } else if after.legal_moves().is_empty() {
+        "#"
+    } else {
+        "+"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_san() {
+        let position = Position::default();
+        assert_eq!(parse_san(&position, "e4").unwrap().to, (3, 4));
+        assert_eq!(parse_san(&position, "Nf3").unwrap().from, (0, 6));
+        assert_eq!(parse_san(&position, "e5"), Err(SanError::IllegalMove));
+        assert_eq!(parse_san(&position, "Zz9"), Err(SanError::Malformed));
+
+        // The c3 knight is pinned, so Ne2 can only mean the g1 knight
+        let position = Position::from_fen("4k3/8/8/b7/8/2N5/8/4K1N1 w - - 0 1").unwrap();
+        assert_eq!(parse_san(&position, "Ne2").unwrap().from, (0, 6));
+
+        // Without the pin it's ambiguous, and each disambiguation resolves it
+        let position = Position::from_fen("4k3/8/8/8/8/2N5/8/4K1N1 w - - 0 1").unwrap();
+        assert_eq!(parse_san(&position, "Ne2"), Err(SanError::AmbiguousMove));
+        assert_eq!(parse_san(&position, "Nce2").unwrap().from, (2, 2));
+        assert_eq!(parse_san(&position, "N1e2").unwrap().from, (0, 6));
+        assert_eq!(parse_san(&position, "Ng1e2").unwrap().from, (0, 6));
+
+        let position = Position::from_fen("r3k3/1P6/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert_eq!(parse_san(&position, "bxa8=Q+").unwrap().promotion, Some(PieceName::Queen));
+        assert_eq!(parse_san(&position, "bxa8=Q").unwrap().promotion, Some(PieceName::Queen));
+        assert_eq!(parse_san(&position, "bxa8=Q#"), Err(SanError::WrongCheckMarker));
+        assert_eq!(parse_san(&position, "b8=N+"), Err(SanError::WrongCheckMarker));
+        assert!(parse_san(&position, "b8=Q+!?").is_ok());
+        assert_eq!(parse_san(&position, "b8N").unwrap().promotion, Some(PieceName::Knight));
+        assert_eq!(parse_san(&position, "b8"), Err(SanError::IllegalMove));
+        assert!(parse_san(&position, "O-O").unwrap().is_castle());
+        assert_eq!(parse_san(&position, "O-O-O"), Err(SanError::IllegalMove));
+
+        // A capture has to be written as one, and a quiet move can't claim to be one
+        let position = Position::from_fen("4k3/8/5p2/8/3pN3/8/2P5/4K3 w - - 0 1").unwrap();
+        assert_eq!(parse_san(&position, "Nf6+"), Err(SanError::IllegalMove));
+        assert!(parse_san(&position, "Nxf6+").is_ok());
+        assert_eq!(parse_san(&position, "d3"), Err(SanError::IllegalMove));
+        assert_eq!(parse_san(&position, "Nxd6"), Err(SanError::IllegalMove));
+        assert!(parse_san(&position, "c3").is_ok());
+    }
+
+    #[test]
+    fn test_move_to_san() {
+        let san_of = |fen: &str, uci: &str| {
+            let position = Position::from_fen(fen).unwrap();
+            move_to_san(&position, &position.parse_uci(uci).unwrap())
+        };
+
+        assert_eq!(san_of("4k3/8/8/8/8/2N5/8/4K1N1 w - - 0 1", "g1e2"), "Nge2");
+        assert_eq!(san_of("4k3/8/8/b7/8/2N5/8/4K1N1 w - - 0 1", "g1e2"), "Ne2");
+        assert_eq!(san_of("4k3/8/8/8/R7/8/8/R3K3 w - - 0 1", "a4a2"), "R4a2");
+        assert_eq!(san_of("8/8/1k6/8/4Q2Q/8/8/K6Q w - - 0 1", "h4e1"), "Qh4e1");
+        assert_eq!(san_of("4k3/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"), "O-O");
+        assert_eq!(san_of("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7a8q"), "bxa8=Q+");
+        assert_eq!(san_of("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8n"), "b8=N");
+        assert_eq!(san_of("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"), "Ra8#");
+        assert_eq!(san_of("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), "exd6");
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+
+use super::fen_error::read_valid_fen;
+use super::move_notation::{parse_move, MoveStyle};
+use super::notation_error::NotationError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolutionError {
+    Notation(NotationError),
+    NotMate(String),
+    WrongMateLength { theme: String, plies: usize }
+}
+
+impl fmt::Display for SolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolutionError::Notation(error) => write!(f, "{}", error),
+            SolutionError::NotMate(theme) => write!(f, "has the '{}' theme but doesn't end in checkmate", theme),
+            SolutionError::WrongMateLength { theme, plies } => {
+                write!(f, "has the '{}' theme but its solution is {} plies long", theme, plies)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SolutionError {}
+
+impl From<NotationError> for SolutionError {
+    fn from(error: NotationError) -> Self {
+        SolutionError::Notation(error)
+    }
+}
+
+// lichess stops counting at mateIn5, which it gives every mate of five moves or more
+const LONGEST_MATE_THEME: usize = 5;
+
+// Plies the solver and opponent play for a mateInN theme, with the solver moving first and last
+fn mate_theme_plies(theme: &str) -> Option<usize> {
+    let moves: usize = theme.strip_prefix("mateIn")?.parse().ok()?;
+    (moves > 0).then(|| moves * 2 - 1)
+}
+
+fn fits_mate_theme(theme_plies: usize, plies: usize) -> bool {
+    if theme_plies >= LONGEST_MATE_THEME * 2 - 1 {
+        plies >= theme_plies
+    } else {
+        plies == theme_plies
+    }
+}
+
+// Replays the solution from the FEN and checks that the final position matches the themes
+pub fn check_solution(fen: &str, solution: &[String], themes: &[String]) -> Result<(), SolutionError> {
+    let mut position = read_valid_fen(fen)?;
+
+    for (i, uci) in solution.iter().enumerate() {
+        let mv = parse_move(&position, uci, MoveStyle::Uci).map_err(|e| NotationError::new(Some(i + 1), uci, e.into()))?;
+        position.make_move(&mv);
+    }
+
+    let is_mate = position.is_in_check(position.turn) && position.legal_moves().is_empty();
+    for theme in themes {
+        let mate_plies = mate_theme_plies(theme);
+        if (theme == "mate" || mate_plies.is_some()) && !is_mate {
+            return Err(SolutionError::NotMate(theme.clone()));
+        }
+        if mate_plies.is_some_and(|plies| !fits_mate_theme(plies, solution.len())) {
+            return Err(SolutionError::WrongMateLength { theme: theme.clone(), plies: solution.len() });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation_utils::notation_error::NotationErrorReason;
+
+    fn to_str_vec(v: Vec<&str>) -> Vec<String> {
+        v.iter().map(|&s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_check_solution() {
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        let mate_in_1 = to_str_vec(vec!["mate", "mateIn1", "endgame"]);
+
+        assert_eq!(check_solution(fen, &to_str_vec(vec!["a1a8"]), &mate_in_1), Ok(()));
+        assert_eq!(
+            check_solution(fen, &to_str_vec(vec!["a1a7"]), &mate_in_1),
+            Err(SolutionError::NotMate("mate".to_string()))
+        );
+        assert_eq!(
+            check_solution(fen, &to_str_vec(vec!["a1a8"]), &to_str_vec(vec!["mateIn2"])),
+            Err(SolutionError::WrongMateLength { theme: "mateIn2".to_string(), plies: 1 })
+        );
+        assert_eq!(
+            check_solution(fen, &to_str_vec(vec!["a1a8", "g8h7"]), &mate_in_1),
+            Err(SolutionError::Notation(NotationError::new(Some(2), "g8h7", NotationErrorReason::IllegalMove)))
+        );
+        assert_eq!(
+            check_solution(fen, &to_str_vec(vec!["a1"]), &mate_in_1),
+            Err(SolutionError::Notation(NotationError::new(Some(1), "a1", NotationErrorReason::Malformed)))
+        );
+        assert!(check_solution("6k1/5ppp/8/8/8/8/8/R7 w - - 0 1", &to_str_vec(vec!["a1a8"]), &mate_in_1).is_err());
+
+        // mateIn5 is five moves or more, so a six move mate still has it
+        let mate_in_5 = to_str_vec(vec!["mate", "mateIn5"]);
+        let shuffles = vec!["a1b1", "g8h8", "b1a1", "h8g8", "a1b1", "g8h8", "b1a1", "h8g8", "a1b1", "g8h8", "b1b8"];
+        assert_eq!(check_solution(fen, &to_str_vec(shuffles), &mate_in_5), Ok(()));
+        assert_eq!(
+            check_solution(fen, &to_str_vec(vec!["a1b1", "g8h8", "b1a1", "h8g8", "a1a8"]), &mate_in_5),
+            Err(SolutionError::WrongMateLength { theme: "mateIn5".to_string(), plies: 5 })
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use super::position::{CastlingSide, Piece, PieceColor, PieceName, Position};
+
+struct ZobristKeys {
+    pieces: [[u64; 64]; 12],
+    // Indexed by [color][side][rook file], so Chess960 rights hash apart from standard ones
+    castling: [[[u64; 8]; 2]; 2],
+    en_passant: [u64; 8],
+    black_to_move: u64
+}
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+// Generated at compile time from a fixed seed, so hashes stay the same between runs and builds
+const fn generate_keys() -> ZobristKeys {
+    let mut keys = ZobristKeys {
+        pieces: [[0; 64]; 12],
+        castling: [[[0; 8]; 2]; 2],
+        en_passant: [0; 8],
+        black_to_move: 0
+    };
+    let mut state = 0x5EED;
+    let mut key;
+
+    let mut piece = 0;
+    while piece < 12 {
+        let mut square = 0;
+        while square < 64 {
+            (state, key) = splitmix64(state);
+            keys.pieces[piece][square] = key;
+            square += 1;
+        }
+        piece += 1;
+    }
+
+    let mut i = 0;
+    while i < 32 {
+        (state, key) = splitmix64(state);
+        keys.castling[i / 16][i / 8 % 2][i % 8] = key;
+        i += 1;
+    }
+
+    let mut file = 0;
+    while file < 8 {
+        (state, key) = splitmix64(state);
+        keys.en_passant[file] = key;
+        file += 1;
+    }
+
+    (_, keys.black_to_move) = splitmix64(state);
+    keys
+}
+
+const KEYS: ZobristKeys = generate_keys();
+
+fn piece_index(piece: Piece) -> usize {
+    let name = match piece.name {
+        PieceName::Pawn => 0,
+        PieceName::Knight => 1,
+        PieceName::Bishop => 2,
+        PieceName::Rook => 3,
+        PieceName::Queen => 4,
+        PieceName::King => 5
+    };
+    if piece.color == PieceColor::White { name } else { name + 6 }
+}
+
+impl Position {
+    // Ignores the move counters, and en passant squares no pawn can capture on,
+    // so the same position hashes the same whichever FEN or move order it came from
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = 0;
+
+        for (rank, row) in self.board.iter().enumerate() {
+            for (file, piece) in row.iter().enumerate() {
+                if let Some(piece) = piece {
+                    hash ^= KEYS.pieces[piece_index(*piece)][rank * 8 + file];
+                }
+            }
+        }
+
+        for (color_index, color) in [PieceColor::White, PieceColor::Black].into_iter().enumerate() {
+            for (side_index, side) in [CastlingSide::King, CastlingSide::Queen].into_iter().enumerate() {
+                if let Some(file) = self.castling.rook_file(color, side) {
+                    hash ^= KEYS.castling[color_index][side_index][file];
+                }
+            }
+        }
+
+        if let Some((rank, file)) = self.en_passant {
+            let pawn_rank = if self.turn == PieceColor::White { rank.checked_sub(1) } else { Some(rank + 1).filter(|&rank| rank < 8) };
+            let capturer = Some(Piece { name: PieceName::Pawn, color: self.turn });
+            let can_capture = pawn_rank.is_some_and(|pawn_rank| {
+                [file.checked_sub(1), Some(file + 1).filter(|&file| file < 8)]
+                    .into_iter()
+                    .flatten()
+                    .any(|from_file| self.board[pawn_rank][from_file] == capturer)
+            });
+            if can_capture {
+                hash ^= KEYS.en_passant[file];
+            }
+        }
+
+        if self.turn == PieceColor::Black {
+            hash ^= KEYS.black_to_move;
+        }
+
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation_utils::san::parse_san;
+
+    fn play(sans: &[&str]) -> Position {
+        let mut position = Position::default();
+        for san in sans {
+            let mv = parse_san(&position, san).unwrap();
+            position.make_move(&mv);
+        }
+        position
+    }
+
+    #[test]
+    fn test_zobrist_hash() {
+        let hash_of = |fen: &str| Position::from_fen(fen).unwrap().zobrist_hash();
+
+        // Transpositions and move counters don't matter
+        assert_eq!(play(&["Nf3", "Nc6", "Nc3", "Nf6"]).zobrist_hash(), play(&["Nc3", "Nf6", "Nf3", "Nc6"]).zobrist_hash());
+        assert_eq!(hash_of("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), hash_of("4k3/8/8/8/8/8/8/4K3 w - - 12 40"));
+
+        // An en passant square only counts when it can be used
+        assert_eq!(hash_of("4k3/8/8/3p4/8/8/8/4K3 w - d6 0 1"), hash_of("4k3/8/8/3p4/8/8/8/4K3 w - - 0 1"));
+        assert_ne!(hash_of("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1"), hash_of("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1"));
+
+        assert_ne!(hash_of("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), hash_of("4k3/8/8/8/8/8/8/4K3 b - - 0 1"));
+        assert_ne!(hash_of("4k3/8/8/8/8/8/8/4K2R w K - 0 1"), hash_of("4k3/8/8/8/8/8/8/4K2R w - - 0 1"));
+        assert_ne!(hash_of("4k3/8/8/8/8/8/8/4K2R w - - 0 1"), hash_of("4k3/8/8/8/8/8/8/4KR2 w - - 0 1"));
+    }
+}
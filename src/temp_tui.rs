@@ -1,19 +1,110 @@
 use std::error::Error;
+use std::fs;
 use std::io::{self, Write};
+use std::path::Path;
 
 use regex::Regex;
 
 use crate::api_requests;
 
 use crate::api_requests::json_objects::Puzzle;
-use api_requests::{get_from_ids::get_from_ids, get_last_n_incorrect::get_last_n_incorrect, post_overwrite::post_overwrite};
+use crate::database::puzzle_store::PuzzleStore;
+use crate::exports::epd_writer::epd_record;
+use crate::exports::html_worksheet::html_worksheet;
+use crate::exports::latex_document::latex_document;
+use crate::exports::svg_diagram::{puzzle_svg, SvgOptions};
+use crate::imports::chess_com_pgn::read_chess_com_pgn;
+use crate::imports::epd_reader::read_epd;
+use crate::imports::puzzle_db::{find_in_puzzle_db, open_puzzle_db, read_puzzle_db, DbFilter};
+use crate::notation_utils::move_notation::{parse_move, MoveStyle, Notation, PieceLetters};
+use crate::notation_utils::notation_error::NotationError;
+use crate::notation_utils::fen_error::read_valid_fen;
+use crate::notation_utils::material::PositionFilter;
+use crate::notation_utils::perft::divide;
+use crate::notation_utils::position::Position;
+use api_requests::{get_from_ids::get_from_ids, get_last_n_incorrect::get_last_n_incorrect, post_overwrite::{post_overwrite, skip_unbuildable}};
 
+use crate::utils::board_diagram::board_diagram;
 use crate::utils::termcolors::{Color, color};
 
+fn skip_invalid(puzzles: Vec<Puzzle>) -> Vec<Puzzle> {
+    puzzles
+        .into_iter()
+        .filter(|puzzle| match puzzle.validate() {
+            Ok(()) => true,
+            Err(e) => {
+                let warning_msg = format!("Not staging {}: {}", puzzle.link(), e);
+                eprintln!("{}", color(&warning_msg, Color::Yellow));
+                false
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+enum ExportFormat {
+    Svg,
+    Html,
+    Latex,
+    Epd
+}
+
+impl ExportFormat {
+    const ALL: [ExportFormat; 4] = [ExportFormat::Svg, ExportFormat::Html, ExportFormat::Latex, ExportFormat::Epd];
+
+    fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Svg => "SVG diagrams, one file per puzzle",
+            ExportFormat::Html => "HTML worksheet with an answer key",
+            ExportFormat::Latex => "LaTeX document for the xskak and chessboard packages",
+            ExportFormat::Epd => "EPD file, one record per puzzle"
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ImportFormat {
+    Epd,
+    ChessComPgn,
+    PuzzleDb
+}
+
+impl ImportFormat {
+    const ALL: [ImportFormat; 3] = [ImportFormat::Epd, ImportFormat::ChessComPgn, ImportFormat::PuzzleDb];
+
+    fn name(self) -> &'static str {
+        match self {
+            ImportFormat::Epd => "EPD file (bm or pv as the solution)",
+            ImportFormat::ChessComPgn => "chess.com puzzle PGN, from a file or pasted",
+            ImportFormat::PuzzleDb => "lichess puzzle database (.csv or .csv.zst), filtered by theme, rating, opening, popularity and plays"
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum StoreAction {
+    Search,
+    Fill
+}
+
+impl StoreAction {
+    const ALL: [StoreAction; 2] = [StoreAction::Search, StoreAction::Fill];
+
+    fn name(self) -> &'static str {
+        match self {
+            StoreAction::Search => "Search the local database and stage a random sample of the results",
+            StoreAction::Fill => "Add or update puzzles from the lichess puzzle database (.csv or .csv.zst)"
+        }
+    }
+}
+
 pub struct App {
     pat: String,
     study_id: String,
     puzzles: Vec<Puzzle>,
+    notation: Notation,
+    store: Option<PuzzleStore>,
+    position_info: bool,
     is_data_stale: bool
 }
 
@@ -23,14 +114,13 @@ impl App {
             pat: "".to_string(),
             study_id: "".to_string(),
             puzzles: Vec::new(),
+            notation: Notation::default(),
+            store: None,
+            position_info: false,
             is_data_stale: false
         }
     }
 
-    fn get_staged_ids(&self) -> Vec<String> {
-        self.puzzles.iter().map(|puzzle| puzzle.id.clone()).collect()
-    }
-
     fn state_message(&self) {
         println!("\n{}{}", color("PAT: ", Color::Cyan), self.pat);
         if self.study_id.is_empty() {
@@ -38,7 +128,8 @@ impl App {
         } else {
             println!("{}{}", color("Study ID: ", Color::Cyan), self.study_id);
         }
-        let staged_puzzles_color = if self.puzzles.len() > 0 { Color::Cyan } else { Color::Yellow };
+        let staged_puzzles_color = if !self.puzzles.is_empty() { Color::Cyan } else { Color::Yellow };
+        println!("{}{}", color("Notation: ", Color::Cyan), self.notation.name());
         println!("{}/64 puzzles staged", color(&format!("{}", self.puzzles.len()), staged_puzzles_color));
     }
 
@@ -85,20 +176,27 @@ impl App {
         println!("h - show this menu");
         println!("p - change PAT");
         println!("s - set/change study ID");
+        println!("n - change how moves are written in study comments (notation and piece letters)");
+        println!("m - toggle material, phase and piece count lines in study comments");
+        println!("k - keep only the staged puzzles matching a material, phase or piece count filter");
         println!("f - autofill puzzle set with your account's recent incorrect puzzles*");
         println!("[puzzle ID]... - add one or more puzzles by their IDs (whitespace or comma delimited)*");
+        println!("v - preview a staged puzzle and step through its solution");
         println!("u - upload all staged puzzles to the current study ID*");
+        println!("e - export staged puzzles to files");
+        println!("i - import and stage puzzles from EPD, chess.com PGN (file or pasted) or the lichess puzzle database");
+        println!("l - search the local puzzle database, or fill it from the lichess puzzle database");
+        println!("d - debug move generation by counting moves (perft divide) from a FEN or staged puzzle");
 
         println!("\n*uses api requests, will involve some delay");
     }
 
     fn get_initial_user_pat(&mut self) {
         println!("Welcome. To get started, enter a lichess PAT, or press [ENTER] if you're confused.");
+        let re = Regex::new(r"^lip_[a-zA-Z0-9]{20}$").unwrap();
         loop {
             let input = self.prompt();
     
-            let re = Regex::new(r"^lip_[a-zA-Z0-9]{20}$").unwrap();
-    
             if re.is_match(&input) {
                 println!("Using PAT {}. Note that this won't be validated until an authenticated request is sent.", input);
                 self.pat = input.to_string();
@@ -112,12 +210,11 @@ impl App {
     }
 
     fn get_user_pat(&mut self) {
+        let re = Regex::new(r"^lip_[a-zA-Z0-9]{20}$").unwrap();
         loop {
             println!("Paste your PAT below.");
             let input = self.prompt();
     
-            let re = Regex::new(r"^lip_[a-zA-Z0-9]{20}$").unwrap();
-    
             if re.is_match(&input) {
                 println!("Using PAT {}.", input);
                 self.pat = input.to_string();
@@ -130,12 +227,11 @@ impl App {
 
     fn get_study_id(&mut self) {
         self.is_data_stale = false;
+        let re = Regex::new(r"^[a-zA-Z0-9]{8}$").unwrap();
         loop {
             println!("Paste the study ID below");
             let input = self.prompt();
     
-            let re = Regex::new(r"^[a-zA-Z0-9]{8}$").unwrap();
-    
             if re.is_match(&input) {
                 println!("Set target study ID to {}.", input);
                 self.study_id = input.to_string();
@@ -146,6 +242,410 @@ impl App {
         }
     }
 
+    fn choose_option<T: Copy>(&self, title: &str, options: &[T], name: fn(T) -> &'static str) -> T {
+        loop {
+            println!("{}", title);
+            for (i, &option) in options.iter().enumerate() {
+                println!("{} - {}", i + 1, name(option));
+            }
+            let input = self.prompt();
+
+            match input.parse::<usize>() {
+                Ok(n) if (1..=options.len()).contains(&n) => return options[n - 1],
+                _ => eprintln!("{}", color("Failed to parse input (enter one of the numbers above)\n", Color::Yellow))
+            }
+        }
+    }
+
+    fn confirm(&self, question: &str) -> bool {
+        println!("{} (y/n)", question);
+        matches!(self.prompt().as_str(), "y" | "Y")
+    }
+
+    fn set_notation(&mut self) {
+        let style = self.choose_option("Choose how moves are written in study comments", &MoveStyle::ALL, MoveStyle::name);
+        let letters = if matches!(style, MoveStyle::San | MoveStyle::Lan) {
+            self.choose_option("Choose the piece letters", &PieceLetters::ALL, PieceLetters::name)
+        } else {
+            PieceLetters::English
+        };
+        self.notation = Notation { style, letters };
+        self.is_data_stale = false;
+        println!("Using {} notation.", self.notation.name());
+    }
+
+    fn toggle_position_info(&mut self) {
+        self.position_info = !self.position_info;
+        self.is_data_stale = false;
+        if self.position_info {
+            println!("Study comments will include each puzzle's material, phase and piece count");
+        } else {
+            println!("Study comments won't include material, phase or piece count");
+        }
+    }
+
+    fn get_position_filter(&self) -> Result<PositionFilter, Box<dyn Error>> {
+        println!("Enter any of a material pattern (e.g. 'KRvKR'; pawns are ignored and '*' allows more pieces, as in 'KQ*vKQ*'), \
+                a phase (opening, middlegame or endgame) and a maximum piece count, space delimited, or press [ENTER] for any");
+        Ok(PositionFilter::parse(&self.prompt())?)
+    }
+
+    fn keep_matching(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.puzzles.is_empty() {
+            return Err(Box::from("Must stage at least one puzzle before filtering"));
+        }
+        let filter = self.get_position_filter()?;
+        let before = self.puzzles.len();
+        self.puzzles.retain(|puzzle| Position::from_fen(&puzzle.fen).is_ok_and(|position| filter.matches(&position)));
+        self.is_data_stale = false;
+
+        let removed = before - self.puzzles.len();
+        let plural_char = if removed == 1 { "" } else { "s" };
+        println!("Removed {} puzzle{}, {} left", removed, plural_char, self.puzzles.len());
+        Ok(())
+    }
+
+    fn preview(&self) -> Result<(), Box<dyn Error>> {
+        if self.puzzles.is_empty() {
+            return Err(Box::from("Must stage at least one puzzle before previewing"));
+        }
+        println!("Enter the ID or number (1-{}) of a staged puzzle", self.puzzles.len());
+        let input = self.prompt();
+        let puzzle = match input.parse::<usize>() {
+            Ok(n) if (1..=self.puzzles.len()).contains(&n) => &self.puzzles[n - 1],
+            _ => self.puzzles
+                .iter()
+                .find(|puzzle| puzzle.id == input)
+                .ok_or_else(|| format!("No staged puzzle matches '{}'", input))?
+        };
+
+        let mut position = read_valid_fen(&puzzle.fen)?;
+        let perspective = position.turn;
+        let mut last_move = position.before_double_push().map(|(_, mv)| mv);
+        let mut coordinates = true;
+        println!("{}", puzzle.info_comment(self.position_info));
+
+        let mut ply = 0;
+        loop {
+            println!("\n{}\n", board_diagram(&position, perspective, last_move.as_ref(), coordinates));
+            if ply == puzzle.solution.len() {
+                println!("End of solution");
+                return Ok(());
+            }
+
+            println!("[ENTER] - next move, c - toggle coordinates, q - stop previewing");
+            match self.prompt().as_str() {
+                "q" | "Q" => return Ok(()),
+                "c" | "C" => {
+                    coordinates = !coordinates;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let uci = &puzzle.solution[ply];
+            let mv = parse_move(&position, uci, MoveStyle::Uci).map_err(|e| NotationError::new(Some(ply + 1), uci, e.into()))?;
+            println!("{} {}", color(&format!("{}.", ply + 1), Color::Cyan), self.notation.format(&position, &mv));
+            position.make_move(&mv);
+            last_move = Some(mv);
+            ply += 1;
+        }
+    }
+
+    fn export(&self) -> Result<(), Box<dyn Error>> {
+        if self.puzzles.is_empty() {
+            return Err(Box::from("Must stage at least one puzzle before exporting"));
+        }
+        match self.choose_option("Choose an export format", &ExportFormat::ALL, ExportFormat::name) {
+            ExportFormat::Svg => self.export_svg(),
+            ExportFormat::Html => self.export_html(),
+            ExportFormat::Latex => self.export_latex(),
+            ExportFormat::Epd => self.export_epd()
+        }
+    }
+
+    // Exports number puzzles from 1 in this list's order. Uploading leaves out puzzles it can't build,
+    // so exports do too, and puzzle N in any export is chapter N of the study
+    fn chapter_puzzles(&self) -> Vec<Puzzle> {
+        skip_unbuildable(self.puzzles.clone(), self.notation, self.position_info)
+    }
+
+    fn export_svg(&self) -> Result<(), Box<dyn Error>> {
+        println!("Enter a directory to write the diagrams to");
+        let directory = self.prompt();
+        fs::create_dir_all(&directory)?;
+
+        let options = SvgOptions {
+            highlight_last_move: self.confirm("Highlight the last move?"),
+            solution_arrows: self.confirm("Draw arrows for the solution?")
+        };
+
+        let mut written = 0;
+        for (i, puzzle) in self.chapter_puzzles().iter().enumerate() {
+            match puzzle_svg(puzzle, options) {
+                Ok(svg) => {
+                    fs::write(Path::new(&directory).join(format!("puzzle_{}.svg", i + 1)), svg)?;
+                    written += 1;
+                }
+                Err(e) => {
+                    let warning_msg = format!("Skipping {}: {}", puzzle.link(), e);
+                    eprintln!("{}", color(&warning_msg, Color::Yellow));
+                }
+            }
+        }
+        println!("Wrote {} diagrams to {}", written, directory);
+        Ok(())
+    }
+
+    fn export_html(&self) -> Result<(), Box<dyn Error>> {
+        println!("Enter a file to write the worksheet to");
+        let path = self.prompt();
+        let puzzles = self.chapter_puzzles();
+        fs::write(&path, html_worksheet(&puzzles, self.notation.letters))?;
+
+        let plural_char = if puzzles.len() == 1 { "" } else { "s" };
+        println!("Wrote a worksheet of {} puzzle{} to {}", puzzles.len(), plural_char, path);
+        Ok(())
+    }
+
+    fn export_latex(&self) -> Result<(), Box<dyn Error>> {
+        println!("Enter a .tex file to write the document to");
+        let path = self.prompt();
+        let puzzles = self.chapter_puzzles();
+        fs::write(&path, latex_document(&puzzles))?;
+
+        let plural_char = if puzzles.len() == 1 { "" } else { "s" };
+        println!("Wrote a document of {} puzzle{} to {}", puzzles.len(), plural_char, path);
+        Ok(())
+    }
+
+    fn export_epd(&self) -> Result<(), Box<dyn Error>> {
+        println!("Enter an .epd file to write the records to");
+        let path = self.prompt();
+
+        let mut records = String::new();
+        let mut written = 0;
+        for puzzle in &self.chapter_puzzles() {
+            match epd_record(puzzle) {
+                Ok(record) => {
+                    records.push_str(&record);
+                    records.push('\n');
+                    written += 1;
+                }
+                Err(e) => {
+                    let warning_msg = format!("Skipping {}: {}", puzzle.link(), e);
+                    eprintln!("{}", color(&warning_msg, Color::Yellow));
+                }
+            }
+        }
+        fs::write(&path, records)?;
+        println!("Wrote {} records to {}", written, path);
+        Ok(())
+    }
+
+    fn import(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.puzzles.len() >= 64 {
+            return Err(Box::from("Stage is already full; use 'c' to clear it first"));
+        }
+        let puzzles = match self.choose_option("Choose an import format", &ImportFormat::ALL, ImportFormat::name) {
+            ImportFormat::Epd => {
+                println!("Enter a file to import from");
+                read_epd(&fs::read_to_string(self.prompt())?)?
+            }
+            ImportFormat::ChessComPgn => {
+                println!("Enter a file to import from, or paste the PGN and finish with a line containing only 'end'");
+                let input = self.prompt();
+                // Anything that isn't a file is taken as the first line of a pasted PGN, whatever it starts with
+                let text = if Path::new(&input).is_file() {
+                    fs::read_to_string(input)?
+                } else {
+                    self.read_pasted_block(input)
+                };
+                read_chess_com_pgn(&text)?
+            }
+            ImportFormat::PuzzleDb => {
+                println!("Enter the path to lichess_db_puzzle.csv or lichess_db_puzzle.csv.zst");
+                let path = self.prompt();
+                let filter = self.get_db_filter()?;
+                find_in_puzzle_db(open_puzzle_db(&path, true)?, &filter, 64 - self.puzzles.len(), &self.puzzles)
+            }
+        };
+        self.stage_imported(puzzles);
+        Ok(())
+    }
+
+    fn get_db_filter(&self) -> Result<DbFilter, Box<dyn Error>> {
+        println!("Enter the themes puzzles must all have, space delimited (e.g. 'fork endgame'), or press [ENTER] for any");
+        let themes = self.prompt().split_whitespace().map(str::to_string).collect();
+
+        println!("Enter a rating range (e.g. '1700-1900', '1700-' or '-1900'), or press [ENTER] for any");
+        let input = self.prompt();
+        let (min, max) = input.split_once('-').unwrap_or((&input, &input));
+        let parse_bound = |bound: &str| match bound.trim() {
+            "" => Ok(None),
+            bound => bound.parse::<i32>().map(Some).map_err(|_| format!("'{}' isn't a number", bound))
+        };
+        let (min_rating, max_rating) = (parse_bound(min)?, parse_bound(max)?);
+
+        println!("Enter opening tags puzzles can come from, space delimited (e.g. 'Sicilian_Defense'), or press [ENTER] for any");
+        let openings = self.prompt().split_whitespace().map(str::to_string).collect();
+
+        println!("Enter a minimum popularity (-100 to 100) and play count (e.g. '80 1000'), or press [ENTER] for any");
+        let input = self.prompt();
+        let mut minimums = input.split_whitespace();
+        let min_popularity = parse_bound(minimums.next().unwrap_or(""))?;
+        let min_plays = parse_bound(minimums.next().unwrap_or(""))?;
+
+        let position = self.get_position_filter()?;
+
+        Ok(DbFilter { themes, openings, min_rating, max_rating, min_popularity, min_plays, position })
+    }
+
+    // Pasted PGNs have blank lines between the headers and the moves, so those can't end the input
+    fn read_pasted_block(&self, first_line: String) -> String {
+        let mut text = first_line;
+        text.push('\n');
+        for line in io::stdin().lines() {
+            let line = line.expect("Failed to read input");
+            if line.trim() == "end" {
+                break;
+            }
+            text.push_str(&line);
+            text.push('\n');
+        }
+        text
+    }
+
+    // Puzzles from files get the same duplicate and solution checks as ones fetched from lichess
+    fn stage_imported(&mut self, puzzles: Vec<Puzzle>) {
+        self.is_data_stale = false;
+        let mut fresh: Vec<Puzzle> = Vec::new();
+        let mut total_duplicates = 0;
+        for puzzle in puzzles {
+            if self.puzzles.iter().chain(&fresh).any(|other| puzzle.is_duplicate_of(other)) {
+                total_duplicates += 1;
+            } else {
+                fresh.push(puzzle);
+            }
+        }
+        let plural_char = if total_duplicates == 1 { "" } else { "s" };
+        if total_duplicates > 0 { println!("Skipping {} duplicate puzzle{}", total_duplicates, plural_char); }
+
+        let mut fresh = skip_invalid(fresh);
+        let capacity = 64 - self.puzzles.len();
+        if fresh.len() > capacity {
+            let plural_char = if fresh.len() - capacity == 1 { "" } else { "s" };
+            let warning_msg = format!("Truncated {} puzzle{} that would exceed stage capacity", fresh.len() - capacity, plural_char);
+            println!("{}", color(&warning_msg, Color::Yellow));
+            fresh.truncate(capacity);
+        }
+        match fresh.len() {
+            1 => println!("Staged 1 puzzle"),
+            _ => println!("Staged {} puzzles", fresh.len())
+        }
+        self.puzzles.extend(fresh);
+    }
+
+    // Opened on first use, so the path is only asked for once per session
+    fn open_store(&mut self) -> Result<&mut PuzzleStore, Box<dyn Error>> {
+        if self.store.is_none() {
+            println!("Enter the path of the local puzzle database (created if missing), or press [ENTER] for puzzles.sqlite3");
+            let input = self.prompt();
+            let path = if input.is_empty() { "puzzles.sqlite3" } else { &input };
+            let store = PuzzleStore::open(path)?;
+            println!("Opened {} with {} puzzles", path, store.count()?);
+            self.store = Some(store);
+        }
+        Ok(self.store.as_mut().unwrap())
+    }
+
+    fn local_database(&mut self) -> Result<(), Box<dyn Error>> {
+        self.open_store()?;
+        match self.choose_option("Choose a database action", &StoreAction::ALL, StoreAction::name) {
+            StoreAction::Search => self.stage_from_store(),
+            StoreAction::Fill => {
+                println!("Enter the path to lichess_db_puzzle.csv or lichess_db_puzzle.csv.zst");
+                let reader = open_puzzle_db(&self.prompt(), true)?;
+                let (stored, skipped) = self.open_store()?.insert_all(read_puzzle_db(reader))?;
+                println!("Stored {} puzzles", stored);
+                let plural_char = if skipped == 1 { "" } else { "s" };
+                if skipped > 0 { println!("Skipping {} unreadable or invalid row{}", skipped, plural_char); }
+                Ok(())
+            }
+        }
+    }
+
+    fn stage_from_store(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.puzzles.len() >= 64 {
+            return Err(Box::from("Stage is already full; use 'c' to clear it first"));
+        }
+        let capacity = 64 - self.puzzles.len();
+        println!("How many puzzles should be staged? (1-{})", capacity);
+        let n = match self.prompt().parse::<usize>() {
+            Ok(n) if (1..=capacity).contains(&n) => n,
+            _ => return Err(format!("Must be a number from 1 to {}", capacity).into())
+        };
+        let filter = self.get_db_filter()?;
+        let unseen_only = self.confirm("Only puzzles not staged from the database before?");
+
+        // Some of a sample can be duplicates, or invalid in stores filled before validation,
+        // so the sample keeps growing until there are enough or it holds every match
+        let store = self.store.as_ref().ok_or("The local puzzle database isn't open")?;
+        let mut limit = n + self.puzzles.len();
+        let found = loop {
+            let results = store.search(&filter, unseen_only, limit)?;
+            let is_every_match = results.len() < limit;
+            let mut found: Vec<Puzzle> = Vec::new();
+            for puzzle in results {
+                if found.len() == n {
+                    break;
+                }
+                let is_duplicate = self.puzzles.iter().chain(&found).any(|other| puzzle.is_duplicate_of(other));
+                if !is_duplicate && puzzle.validate().is_ok() {
+                    found.push(puzzle);
+                }
+            }
+            if found.len() == n || is_every_match {
+                break found;
+            }
+            limit *= 2;
+        };
+        if found.len() < n {
+            println!("{}", color(&format!("Only {} puzzles match", found.len()), Color::Yellow));
+        }
+
+        // Only what actually made it onto the stage counts as seen
+        let before = self.puzzles.len();
+        self.stage_imported(found);
+        if let Some(store) = &self.store {
+            store.mark_seen(&self.puzzles[before..])?;
+        }
+        Ok(())
+    }
+
+    fn perft_divide(&self) -> Result<(), Box<dyn Error>> {
+        println!("Paste a FEN or the ID of a staged puzzle");
+        let input = self.prompt();
+        let fen = match self.puzzles.iter().find(|puzzle| puzzle.id == input) {
+            Some(puzzle) => puzzle.fen.clone(),
+            None => input
+        };
+        let position = Position::from_fen(&fen).map_err(|e| format!("'{}' isn't a valid FEN: {}", fen, e))?;
+
+        println!("Enter a depth from 1 to 5");
+        let depth = match self.prompt().parse::<u32>() {
+            Ok(depth) if (1..=5).contains(&depth) => depth,
+            _ => return Err(Box::from("Depth must be a number from 1 to 5"))
+        };
+
+        let counts = divide(&position, depth);
+        for (uci, nodes) in &counts {
+            println!("{}: {}", uci, nodes);
+        }
+        println!("\n{}{}", color("Total: ", Color::Cyan), counts.iter().map(|(_, nodes)| nodes).sum::<u64>());
+        Ok(())
+    }
+
     fn clear_puzzles(&mut self) {
         let plural_char = if self.puzzles.len() == 1 { "" } else { "s" };
         println!("Cleared {} puzzle{}", self.puzzles.len(), plural_char);
@@ -160,7 +660,7 @@ impl App {
         self.is_data_stale = false;
         let n = 64 - self.puzzles.len();
         println!("Autofilling {} puzzles (this may take a while)", n);
-        let puzzles: Vec<Puzzle> = get_last_n_incorrect(self.pat.clone(), n, self.get_staged_ids()).await?;
+        let puzzles: Vec<Puzzle> = skip_invalid(get_last_n_incorrect(self.pat.clone(), n, &self.puzzles).await?);
         match puzzles.len() {
             1 => println!("Staged 1 puzzle"),
             _ => println!("Staged {} puzzles", puzzles.len())
@@ -178,7 +678,7 @@ impl App {
             return Err(Box::from("Must stage at least one puzzle before attempting to upload"));
         }
         println!("Clearing study {} and uploading {} staged puzzles (this may take a while)", self.study_id, self.puzzles.len());
-        post_overwrite(self.pat.clone(), &self.study_id, self.puzzles.clone()).await?;
+        post_overwrite(self.pat.clone(), &self.study_id, self.puzzles.clone(), self.notation, self.position_info).await?;
         self.is_data_stale = true;
         Ok(())
     }
@@ -186,6 +686,9 @@ impl App {
     pub async fn run(&mut self) {
         self.get_initial_user_pat();
 
+        let id_input_re = Regex::new(r"(?i)\b[a-z0-9]{5}\b(?:[, ]\s*)?").unwrap();
+        let id_re = Regex::new(r"(?i)\b[a-z0-9]{5}\b").unwrap();
+
         loop {
             self.state_message();
             println!("\nPlease enter an action (enter 'h' for a list of valid commands)");
@@ -197,6 +700,38 @@ impl App {
                 "p" | "P" => self.get_user_pat(),
                 "s" | "S" => self.get_study_id(),
                 "c" | "C" => self.clear_puzzles(),
+                "n" | "N" => self.set_notation(),
+                "m" | "M" => self.toggle_position_info(),
+                "k" | "K" => {
+                    if let Err(e) = self.keep_matching() {
+                        eprintln!("{}", color(&e.to_string(), Color::Red));
+                    }
+                }
+                "v" | "V" => {
+                    if let Err(e) = self.preview() {
+                        eprintln!("{}", color(&e.to_string(), Color::Red));
+                    }
+                }
+                "e" | "E" => {
+                    if let Err(e) = self.export() {
+                        eprintln!("{}", color(&e.to_string(), Color::Red));
+                    }
+                }
+                "i" | "I" => {
+                    if let Err(e) = self.import() {
+                        eprintln!("{}", color(&e.to_string(), Color::Red));
+                    }
+                }
+                "l" | "L" => {
+                    if let Err(e) = self.local_database() {
+                        eprintln!("{}", color(&e.to_string(), Color::Red));
+                    }
+                }
+                "d" | "D" => {
+                    if let Err(e) = self.perft_divide() {
+                        eprintln!("{}", color(&e.to_string(), Color::Red));
+                    }
+                }
                 "f" | "F" => {
                     if let Err(e) = self.autofill().await {
                         eprintln!("{}", color(&e.to_string(), Color::Red));
@@ -208,22 +743,20 @@ impl App {
                     }
                 },
                 _ => { 
-                    let re = Regex::new(r"(?i)\b[a-z0-9]{5}\b(?:[, ]\s*)?").unwrap();
-                    if re.is_match(&input) {
+                    if id_input_re.is_match(&input) {
                         if self.puzzles.len() >= 64 {
                             eprintln!("{}", color("Stage is already full; use 'c' to clear it first", Color::Red));
                         } else {
                             self.is_data_stale = false;
-                            let re = Regex::new(r"(?i)\b[a-z0-9]{5}\b").unwrap();
-                            let puzzle_ids: Vec<String> = re.find_iter(&input)
+                            let puzzle_ids: Vec<String> = id_re.find_iter(&input)
                                 .map(|mat| mat.as_str().to_string())
                                 .collect();
 
                             let mut puzzles: Vec<Puzzle> = Vec::new();
 
-                            match get_from_ids(puzzle_ids, self.get_staged_ids()).await {
+                            match get_from_ids(puzzle_ids, &self.puzzles).await {
                                 Ok(result) => {
-                                    puzzles = result;
+                                    puzzles = skip_invalid(result);
                                 }
                                 Err(e) => {
                                     eprintln!("{}", color(&e.to_string(), Color::Red));
@@ -231,16 +764,14 @@ impl App {
                             }
 
                             let new_size =  self.puzzles.len() + puzzles.len();
-                            let truncated_set: Vec<Puzzle>;
-                            if new_size > 64 {
+                            let truncated_set: Vec<Puzzle> = if new_size > 64 {
                                 let plural_char = if new_size - 64 == 1 { "" } else { "s" };
                                 let warning_msg = &format!("Truncated {} puzzle{} that would exceed stage capacity", new_size-64, plural_char);
                                 println!("{}", color(warning_msg, Color::Yellow));
-                                truncated_set = puzzles[0..64 - self.puzzles.len()].iter().cloned().collect();
-                                
+                                puzzles[0..64 - self.puzzles.len()].to_vec()
                             } else {
-                                truncated_set = puzzles.clone();
-                            }
+                                puzzles.clone()
+                            };
                             self.puzzles.extend(truncated_set);
                             match puzzles.len() {
                                 1 => println!("Staged 1 puzzle"),
@@ -1,2 +1,3 @@
 pub mod termcolors;
 pub mod progress_bar;
+pub mod board_diagram;
@@ -0,0 +1,94 @@
+use crate::notation_utils::move_notation::solid_figurine;
+use crate::notation_utils::position::{Move, PieceColor, Position, Square};
+
+const LIGHT_SQUARE: u8 = 180;
+const DARK_SQUARE: u8 = 137;
+const LIGHT_HIGHLIGHT: u8 = 186;
+const DARK_HIGHLIGHT: u8 = 143;
+const WHITE_PIECE: u8 = 231;
+const BLACK_PIECE: u8 = 16;
+
+fn render_square(position: &Position, square: Square, highlighted: bool) -> String {
+    let is_light = (square.0 + square.1) % 2 == 1;
+    let background = match (is_light, highlighted) {
+        (true, false) => LIGHT_SQUARE,
+        (false, false) => DARK_SQUARE,
+        (true, true) => LIGHT_HIGHLIGHT,
+        (false, true) => DARK_HIGHLIGHT
+    };
+
+    match position.piece_at(square) {
+        Some(piece) => {
+            let foreground = if piece.color == PieceColor::White { WHITE_PIECE } else { BLACK_PIECE };
+            format!("\x1b[48;5;{}m\x1b[38;5;{}m {} \x1b[0m", background, foreground, solid_figurine(piece.name))
+        }
+        None => format!("\x1b[48;5;{}m   \x1b[0m", background)
+    }
+}
+
+// Draws the board with `perspective`'s pieces at the bottom, highlighting both squares of the last move
+pub fn board_diagram(position: &Position, perspective: PieceColor, last_move: Option<&Move>, coordinates: bool) -> String {
+    let ranks: Vec<usize> = if perspective == PieceColor::White { (0..8).rev().collect() } else { (0..8).collect() };
+    let files: Vec<usize> = if perspective == PieceColor::White { (0..8).collect() } else { (0..8).rev().collect() };
+
+    let mut lines = Vec::new();
+    for &rank in &ranks {
+        let mut line = String::new();
+        if coordinates {
+            line.push_str(&format!("{} ", rank + 1));
+        }
+        for &file in &files {
+            let highlighted = last_move.is_some_and(|mv| mv.from == (rank, file) || mv.to == (rank, file));
+            line.push_str(&render_square(position, (rank, file), highlighted));
+        }
+        lines.push(line);
+    }
+
+    if coordinates {
+        let file_labels: String = files.iter().map(|&file| format!(" {} ", (b'a' + file as u8) as char)).collect();
+        lines.push(format!("  {}", file_labels));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip_escapes(text: &str) -> String {
+        let mut stripped = String::new();
+        let mut in_escape = false;
+        for c in text.chars() {
+            match c {
+                '\x1b' => in_escape = true,
+                'm' if in_escape => in_escape = false,
+                _ if !in_escape => stripped.push(c),
+                _ => {}
+            }
+        }
+        stripped
+    }
+
+    #[test]
+    fn test_board_diagram() {
+        let position = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+
+        let white = strip_escapes(&board_diagram(&position, PieceColor::White, None, true));
+        let lines: Vec<&str> = white.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[0], "8                    ♚    ");
+        assert_eq!(lines[7], "1  ♜                 ♚    ");
+        assert_eq!(lines[8], "   a  b  c  d  e  f  g  h ");
+
+        let black = strip_escapes(&board_diagram(&position, PieceColor::Black, None, false));
+        let lines: Vec<&str> = black.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "    ♚                 ♜ ");
+
+        let mv = position.parse_uci("a1a8").unwrap();
+        let highlighted = board_diagram(&position, PieceColor::White, Some(&mv), false);
+        assert_eq!(highlighted.matches(&format!("48;5;{}m", DARK_HIGHLIGHT)).count(), 1);
+        assert_eq!(highlighted.matches(&format!("48;5;{}m", LIGHT_HIGHLIGHT)).count(), 1);
+    }
+}
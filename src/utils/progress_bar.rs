@@ -1,3 +1,5 @@
+use std::io::{self, Read, Write};
+
 pub const PROGRESS_BAR_WIDTH: usize = 20;
 
 pub fn inner_progress_bar(progress: f32, width: usize) -> String {
@@ -17,3 +19,65 @@ pub fn inner_progress_bar(progress: f32, width: usize) -> String {
 
     bar
 }
+
+// Wraps a reader of known length and redraws a progress bar on `output` whenever another percent
+// of it has been read. Drawing is best effort, so a closed output never fails the read
+pub struct ProgressReader<R: Read, W: Write> {
+    inner: R,
+    output: W,
+    label: String,
+    read: u64,
+    total: u64,
+    percent: i32
+}
+
+impl<R: Read, W: Write> ProgressReader<R, W> {
+    pub fn new(inner: R, total: u64, label: &str, mut output: W) -> Self {
+        let _ = write!(output, "{} [{}] 0% ", label, inner_progress_bar(0.0, PROGRESS_BAR_WIDTH));
+        let _ = output.flush();
+        Self { inner, output, label: label.to_string(), read: 0, total, percent: 0 }
+    }
+}
+
+impl<R: Read, W: Write> Read for ProgressReader<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+
+        let progress = if self.total == 0 { 1.0 } else { self.read as f32 / self.total as f32 };
+        let percent = (progress * 100.0).floor() as i32;
+        if percent != self.percent {
+            self.percent = percent;
+            let bar = inner_progress_bar(progress, PROGRESS_BAR_WIDTH);
+            let _ = write!(self.output, "\x1b[0G{} [{}] {}% ", self.label, bar, percent);
+            let _ = self.output.flush();
+        }
+        Ok(n)
+    }
+}
+
+// Ends the progress bar's line however far it got, since readers are often dropped early
+impl<R: Read, W: Write> Drop for ProgressReader<R, W> {
+    fn drop(&mut self) {
+        let _ = writeln!(self.output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_reader() {
+        let mut output = Vec::new();
+        {
+            let mut reader = ProgressReader::new(&[0u8; 200][..], 200, "Reading", &mut output);
+            let mut buf = [0u8; 50];
+            while reader.read(&mut buf).unwrap() > 0 {}
+        }
+        let drawn = String::from_utf8(output).unwrap();
+        assert!(drawn.starts_with("Reading ["));
+        assert_eq!(drawn.matches("\x1b[0G").count(), 4);
+        assert!(drawn.ends_with("100% \n"));
+    }
+}